@@ -1,62 +1,471 @@
-const DIGIT_STRINGS: [&'static str; 9] = [
-    "one",
-    "two",
-    "three",
-    "four",
-    "five",
-    "six",
-    "seven",
-    "eight",
-    "nine"
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+
+const NUMERALS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+/* first code point of every run of ten decimal digits (general category Nd) as of Unicode 17.0 */
+const DECIMAL_DIGIT_ZEROS: [u32; 77] = [
+    0x00030, 0x00660, 0x006F0, 0x007C0, 0x00966, 0x009E6, 0x00A66, 0x00AE6,
+    0x00B66, 0x00BE6, 0x00C66, 0x00CE6, 0x00D66, 0x00DE6, 0x00E50, 0x00ED0,
+    0x00F20, 0x01040, 0x01090, 0x017E0, 0x01810, 0x01946, 0x019D0, 0x01A80,
+    0x01A90, 0x01B50, 0x01BB0, 0x01C40, 0x01C50, 0x0A620, 0x0A8D0, 0x0A900,
+    0x0A9D0, 0x0A9F0, 0x0AA50, 0x0ABF0, 0x0FF10, 0x104A0, 0x10D30, 0x10D40,
+    0x11066, 0x110F0, 0x11136, 0x111D0, 0x112F0, 0x11450, 0x114D0, 0x11650,
+    0x116C0, 0x116D0, 0x116DA, 0x11730, 0x118E0, 0x11950, 0x11BF0, 0x11C50,
+    0x11D50, 0x11DA0, 0x11DE0, 0x11F50, 0x16130, 0x16A60, 0x16AC0, 0x16B50,
+    0x16D70, 0x1CCF0, 0x1D7CE, 0x1D7D8, 0x1D7E2, 0x1D7EC, 0x1D7F6, 0x1E140,
+    0x1E2F0, 0x1E4F0, 0x1E5F1, 0x1E950, 0x1FBF0
 ];
 
 
-pub fn sum_first_and_last_digits(text: String, use_words: bool) -> u32 {
-    let iter = text.split_whitespace();
-    iter.map(
-        |line| {
-            let digit_indices = get_digit_indices(line, use_words);
-            let digits: [u32; 2] = [digit_indices.first(), digit_indices.last()].map(
-                |digit_index| if let Some((_, term)) = digit_index {
-                    parse_digit(term)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NumeralMode {
+    Ascii,      // only '0' to '9'
+    Unicode     // decimal digits from any script, e.g. '٣' or '७'
+}
+
+
+#[derive(Clone, Copy)]
+pub enum Language {
+    English,
+    German,
+    French,
+    Spanish
+}
+
+impl Language {
+    /* indexed by the digit each word spells */
+    fn digit_words(&self) -> [&'static str; 10] {
+        match self {
+            Language::English => ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"],
+            Language::German => ["null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun"],
+            Language::French => ["zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf"],
+            Language::Spanish => ["cero", "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve"]
+        }
+    }
+}
+
+
+/* The terms that count as digits in a calibration line. Numerals are always included. */
+pub struct DigitVocabulary {
+    terms: Vec<(String, u32)>,
+    numeral_mode: NumeralMode
+}
+
+impl DigitVocabulary {
+    pub fn numerals() -> Self {
+        DigitVocabulary {
+            terms: NUMERALS.into_iter().zip(0..).map(|(numeral, value)| (String::from(numeral), value)).collect(),
+            numeral_mode: NumeralMode::Ascii
+        }
+    }
+
+    pub fn with_numeral_mode(mut self, numeral_mode: NumeralMode) -> Self {
+        self.numeral_mode = numeral_mode;
+        self
+    }
+
+    pub fn spelled(language: Language, include_zero: bool) -> Self {
+        language.digit_words().into_iter().zip(0..).skip(if include_zero { 0 } else { 1 }).fold(
+            Self::numerals(),
+            |vocabulary, (word, value)| vocabulary.with_term(word, value)
+        )
+    }
+
+    pub fn with_term(mut self, term: &str, value: u32) -> Self {
+        if value > 9 {
+            panic!("Tried to add term '{}' for {}, which is not a single digit", term, value)
+        }
+        if term.is_empty() {
+            panic!("Tried to add an empty term for {}", value)
+        }
+        /* a term with two values would read as either, depending on which end the scan starts from */
+        match self.terms.iter().find(|(existing_term, _)| existing_term == term) {
+            Some(&(_, existing_value)) if existing_value != value => panic!(
+                "Tried to add term '{}' for {}, but it already stands for {}", term, value, existing_value
+            ),
+            Some(_) => (),
+            None => self.terms.push((String::from(term), value))
+        }
+        self
+    }
+
+    /* one 'term digit' pair per line, e.g. 'eins 1' */
+    pub fn parse(vocabulary_text: &str) -> Self {
+        vocabulary_text.split('\n').map(|line| line.trim()).filter(|line| !line.is_empty()).fold(
+            Self::numerals(),
+            |vocabulary, line| if let Some(&[term, value_str]) = line.split_whitespace().collect::<Vec<&str>>().get(0..2) {
+                if let Ok(value) = value_str.parse::<u32>() {
+                    vocabulary.with_term(term, value)
                 } else {
-                    0
+                    panic!("Could not parse '{}' as a digit in vocabulary line '{}'", value_str, line)
                 }
-            );
-            digits[0] * 10 + digits[1]
+            } else {
+                panic!("Could not parse term and value from vocabulary line '{}'", line)
+            }
+        )
+    }
+
+    pub fn from_file(path: &str) -> Self {
+        Self::parse(&fs::read_to_string(path).unwrap_or_else(|_| panic!("Could not read {}", path)))
+    }
+
+    fn terms(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.terms.iter().map(|(term, value)| (term.as_str(), *value))
+    }
+}
+
+
+#[derive(Clone, Copy)]
+struct DigitMatch {
+    start: usize,
+    length: usize,
+    value: u32
+}
+
+
+/* Aho-Corasick automaton over a set of patterns, each tagged with the digit it spells */
+struct PatternAutomaton {
+    transitions: Vec<HashMap<char, usize>>,
+    failures: Vec<usize>,
+    outputs: Vec<Vec<(usize, u32)>>,
+    max_pattern_length: usize
+}
+
+impl PatternAutomaton {
+    fn new<'a>(patterns: impl Iterator<Item = (&'a str, u32)>, reversed: bool) -> Self {
+        let mut automaton = PatternAutomaton {
+            transitions: vec![HashMap::new()],
+            failures: vec![0],
+            outputs: vec![Vec::new()],
+            max_pattern_length: 0
+        };
+        for (pattern, value) in patterns {
+            let chars = if reversed { pattern.chars().rev().collect::<Vec<char>>() } else { pattern.chars().collect() };
+            let mut state = 0;
+            for c in chars {
+                state = if let Some(&next_state) = automaton.transitions[state].get(&c) {
+                    next_state
+                } else {
+                    let next_state = automaton.transitions.len();
+                    automaton.transitions.push(HashMap::new());
+                    automaton.failures.push(0);
+                    automaton.outputs.push(Vec::new());
+                    automaton.transitions[state].insert(c, next_state);
+                    next_state
+                };
+            }
+            automaton.outputs[state].push((pattern.len(), value));
+            automaton.max_pattern_length = automaton.max_pattern_length.max(pattern.len());
         }
-    ).sum()
+        /* breadth-first so every failure target is finished before it is used */
+        let mut queue = automaton.transitions[0].values().copied().collect::<VecDeque<usize>>();
+        while let Some(state) = queue.pop_front() {
+            for (c, next_state) in automaton.transitions[state].clone() {
+                let mut fallback = automaton.failures[state];
+                while fallback != 0 && !automaton.transitions[fallback].contains_key(&c) {
+                    fallback = automaton.failures[fallback];
+                }
+                let failure = automaton.transitions[fallback].get(&c).copied().unwrap_or(0);
+                automaton.failures[next_state] = failure;
+                let inherited_outputs = automaton.outputs[failure].clone();
+                automaton.outputs[next_state].extend(inherited_outputs);
+                queue.push_back(next_state);
+            }
+        }
+        automaton
+    }
+
+    fn step(&self, state: usize, c: char) -> usize {
+        let mut state = state;
+        loop {
+            if let Some(&next_state) = self.transitions[state].get(&c) {
+                return next_state
+            } else if state == 0 {
+                return 0
+            }
+            state = self.failures[state];
+        }
+    }
 }
 
 
-fn get_digit_indices(line: &str, use_words: bool) -> Vec<(usize, &str)> {
-    let mut digit_string_indices: Vec<(usize, &str)> = Vec::new();
-    digit_string_indices.extend(line.match_indices(char::is_numeric));
-    if use_words {
-        digit_string_indices.extend(
-            DIGIT_STRINGS.iter().map(
-                |digit_string| line.match_indices(digit_string)
-            ).flatten()
+#[derive(Debug)]
+pub struct MissingDigitsError {
+    pub line_numbers: Vec<usize>
+}
+
+impl fmt::Display for MissingDigitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "No digits found on line(s) {}",
+            self.line_numbers.iter().map(|line_number| line_number.to_string()).collect::<Vec<String>>().join(", ")
         )
     }
-    digit_string_indices.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-    return digit_string_indices
 }
 
 
-fn parse_digit(term: &str) -> u32 {
-    term.parse::<u32>().unwrap_or(
-        match term {
-            "one" => 1,
-            "two" => 2,
-            "three" => 3,
-            "four" => 4,
-            "five" => 5,
-            "six" => 6,
-            "seven" => 7,
-            "eight" => 8,
-            "nine" => 9,
-            _ => 0
+#[derive(Clone)]
+pub struct DigitToken {
+    pub column: usize,
+    pub text: String,
+    pub value: u32
+}
+
+impl fmt::Display for DigitToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.text, self.column)
+    }
+}
+
+
+pub struct CalibrationLine {
+    pub line_number: usize,
+    pub tokens: Vec<DigitToken>,
+    pub first: Option<DigitToken>,
+    pub last: Option<DigitToken>,
+    pub value: Option<u32>
+}
+
+impl CalibrationLine {
+    fn get_fields(&self) -> [String; 5] {
+        let describe = |token: &Option<DigitToken>| token.as_ref().map_or(String::new(), |token| token.to_string());
+        [
+            self.line_number.to_string(),
+            self.tokens.iter().map(|token| token.to_string()).collect::<Vec<String>>().join(" "),
+            describe(&self.first),
+            describe(&self.last),
+            self.value.map_or(String::new(), |value| value.to_string())
+        ]
+    }
+}
+
+
+pub struct CalibrationTrace {
+    pub lines: Vec<CalibrationLine>
+}
+
+impl CalibrationTrace {
+    const HEADERS: [&'static str; 5] = ["line", "tokens", "first", "last", "value"];
+
+    pub fn sum(&self) -> Result<u32, MissingDigitsError> {
+        sum_calibration_values(self.lines.iter().map(|line| (line.line_number, line.value)))
+    }
+
+    pub fn to_table(&self) -> String {
+        let rows = [Self::HEADERS.map(String::from)].into_iter().chain(
+            self.lines.iter().map(|line| line.get_fields())
+        ).collect::<Vec<[String; 5]>>();
+        let widths = (0..5).map(
+            |i| rows.iter().map(|row| row[i].chars().count()).max().unwrap_or(0)
+        ).collect::<Vec<usize>>();
+        rows.iter().map(
+            |row| row.iter().zip(widths.iter()).map(
+                |(field, &width)| format!("{:<width$}", field, width = width)
+            ).collect::<Vec<String>>().join(" | ").trim_end().to_string()
+        ).collect::<Vec<String>>().join("\n")
+    }
+
+    pub fn to_csv(&self) -> String {
+        [Self::HEADERS.join(",")].into_iter().chain(
+            self.lines.iter().map(
+                |line| line.get_fields().map(
+                    |field| format!("\"{}\"", field.replace('"', "\"\""))
+                ).join(",")
+            )
+        ).collect::<Vec<String>>().join("\n")
+    }
+}
+
+
+struct DigitScanner {
+    forward: PatternAutomaton,
+    backward: PatternAutomaton,
+    numeral_mode: NumeralMode
+}
+
+impl DigitScanner {
+    fn new(vocabulary: &DigitVocabulary) -> Self {
+        DigitScanner {
+            forward: PatternAutomaton::new(vocabulary.terms(), false),
+            backward: PatternAutomaton::new(vocabulary.terms(), true),
+            numeral_mode: vocabulary.numeral_mode
         }
+    }
+
+    fn get_non_ascii_numeral_value(&self, c: char) -> Option<u32> {
+        /* ASCII numerals are already patterns in the automata */
+        if self.numeral_mode == NumeralMode::Unicode && !c.is_ascii() {
+            get_decimal_digit_value(c)
+        } else {
+            None
+        }
+    }
+
+    /* When several terms start at the same place, both ends take the longest, so a vocabulary with terms that
+     * prefix each other reads that place the same way from either side.
+     */
+    fn find_first(&self, line: &str) -> Option<DigitMatch> {
+        /* matches are found in order of where they end, so keep going until no later match could start as early */
+        let mut state = 0;
+        let mut first_match: Option<DigitMatch> = None;
+        for (i, c) in line.char_indices() {
+            let end = i + c.len_utf8();
+            if first_match.is_some_and(|first_match| first_match.start + self.forward.max_pattern_length < end) {
+                break;
+            }
+            state = self.forward.step(state, c);
+            let numeral_match = self.get_non_ascii_numeral_value(c).map(|value| (c.len_utf8(), value));
+            for &(length, value) in self.forward.outputs[state].iter().chain(numeral_match.iter()) {
+                if first_match.is_none_or(
+                    |first_match| end - length < first_match.start || (end - length == first_match.start && length > first_match.length)
+                ) {
+                    first_match = Some(DigitMatch { start: end - length, length, value });
+                }
+            }
+        }
+        first_match
+    }
+
+    fn find_last(&self, line: &str) -> Option<DigitMatch> {
+        /* scanning backwards, the first match found is the one that starts last */
+        let mut state = 0;
+        for (i, c) in line.char_indices().rev() {
+            state = self.backward.step(state, c);
+            if let Some(&(length, value)) = self.backward.outputs[state].iter().max_by_key(|(length, _)| *length) {
+                return Some(DigitMatch { start: i, length, value })
+            }
+            if let Some(value) = self.get_non_ascii_numeral_value(c) {
+                return Some(DigitMatch { start: i, length: c.len_utf8(), value })
+            }
+        }
+        None
+    }
+
+    fn find_all(&self, line: &str) -> Vec<DigitMatch> {
+        let mut state = 0;
+        let mut matches = Vec::new();
+        for (i, c) in line.char_indices() {
+            let end = i + c.len_utf8();
+            state = self.forward.step(state, c);
+            let numeral_match = self.get_non_ascii_numeral_value(c).map(|value| (c.len_utf8(), value));
+            matches.extend(
+                self.forward.outputs[state].iter().chain(numeral_match.iter()).map(
+                    |&(length, value)| DigitMatch { start: end - length, length, value }
+                )
+            );
+        }
+        matches.sort_by_key(|digit_match| (digit_match.start, digit_match.length));
+        matches
+    }
+}
+
+
+pub fn sum_first_and_last_digits(text: String, vocabulary: &DigitVocabulary) -> Result<u32, MissingDigitsError> {
+    let scanner = DigitScanner::new(vocabulary);
+    sum_calibration_values(
+        text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()).map(
+            |(i, line)| (
+                i + 1,
+                if let (Some(first), Some(last)) = (scanner.find_first(line), scanner.find_last(line)) {
+                    Some(first.value * 10 + last.value)
+                } else {
+                    None
+                }
+            )
+        )
     )
+}
+
+
+pub fn trace_first_and_last_digits(text: String, vocabulary: &DigitVocabulary) -> CalibrationTrace {
+    let scanner = DigitScanner::new(vocabulary);
+    CalibrationTrace {
+        lines: text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()).map(
+            |(i, line)| {
+                let to_token = |digit_match: DigitMatch| DigitToken {
+                    column: line[..digit_match.start].chars().count(),
+                    text: String::from(&line[digit_match.start..(digit_match.start + digit_match.length)]),
+                    value: digit_match.value
+                };
+                let (first, last) = (scanner.find_first(line).map(to_token), scanner.find_last(line).map(to_token));
+                let value = if let (Some(first), Some(last)) = (&first, &last) {
+                    Some(first.value * 10 + last.value)
+                } else {
+                    None
+                };
+                CalibrationLine {
+                    line_number: i + 1,
+                    tokens: scanner.find_all(line).into_iter().map(to_token).collect(),
+                    first,
+                    last,
+                    value
+                }
+            }
+        ).collect()
+    }
+}
+
+
+fn sum_calibration_values(values: impl Iterator<Item = (usize, Option<u32>)>) -> Result<u32, MissingDigitsError> {
+    let mut sum = 0;
+    let mut line_numbers_missing_digits = Vec::new();
+    for (line_number, value) in values {
+        if let Some(value) = value {
+            sum += value;
+        } else {
+            line_numbers_missing_digits.push(line_number);
+        }
+    }
+    if line_numbers_missing_digits.is_empty() {
+        Ok(sum)
+    } else {
+        Err(MissingDigitsError { line_numbers: line_numbers_missing_digits })
+    }
+}
+
+
+fn get_decimal_digit_value(c: char) -> Option<u32> {
+    let code_point = c as u32;
+    let i = DECIMAL_DIGIT_ZEROS.partition_point(|&zero| zero <= code_point);
+    if i > 0 && code_point - DECIMAL_DIGIT_ZEROS[i - 1] < 10 {
+        Some(code_point - DECIMAL_DIGIT_ZEROS[i - 1])
+    } else {
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terms_starting_at_the_same_place_read_the_same_from_both_ends() {
+        let vocabulary = DigitVocabulary::numerals().with_term("on", 7).with_term("one", 1).with_term("onex", 4);
+        assert_eq!(sum_first_and_last_digits(String::from("one"), &vocabulary).unwrap(), 11);
+        assert_eq!(sum_first_and_last_digits(String::from("onex"), &vocabulary).unwrap(), 44);
+        assert_eq!(sum_first_and_last_digits(String::from("on"), &vocabulary).unwrap(), 77);
+        assert_eq!(sum_first_and_last_digits(String::from("xonexonez"), &vocabulary).unwrap(), 41);
+    }
+
+    #[test]
+    fn repeating_a_term_with_the_same_value_is_allowed() {
+        let vocabulary = DigitVocabulary::numerals().with_term("on", 1).with_term("on", 1);
+        assert_eq!(sum_first_and_last_digits(String::from("on"), &vocabulary).unwrap(), 11);
+    }
+
+    #[test]
+    #[should_panic(expected = "already stands for 1")]
+    fn a_term_cannot_have_two_values() {
+        DigitVocabulary::numerals().with_term("on", 1).with_term("on", 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "already stands for 2")]
+    fn parsed_vocabularies_cannot_redefine_terms() {
+        DigitVocabulary::parse("zwei 2\nzwei 3");
+    }
 }
\ No newline at end of file
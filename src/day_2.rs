@@ -1,60 +1,177 @@
 use std::cmp::max;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 
-struct Game {
+/* Games and grabs are checked when built, so every record prints as text that parses back to the same record:
+ * a game needs at least one grab, a grab at least one colour, and colour names cannot be empty or contain
+ * whitespace, ',' or ';'.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
     id: u32,
     grabs: Vec<Grab>
 }
 
 impl Game {
-    fn is_valid(&self, rgb_limits: (u32, u32, u32)) -> bool {
+    pub fn new(id: u32, grabs: Vec<Grab>) -> Result<Self, ParseGameError> {
+        if grabs.is_empty() {
+            Err(ParseGameError::new("Found no grabs", "game", &format!("Game {}", id)))
+        } else {
+            Ok(Game { id, grabs })
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn grabs(&self) -> &[Grab] {
+        &self.grabs
+    }
+
+    pub fn is_valid(&self, bag: &BTreeMap<String, u32>) -> bool {
         self.grabs.iter().all(
-            |grab| [(grab.red, rgb_limits.0), (grab.green, rgb_limits.1), (grab.blue, rgb_limits.2)].iter().all(
-                |(grab_color, limit)| grab_color.is_none() || grab_color.unwrap() <= *limit
+            |grab| grab.cubes.iter().all(
+                |(color, &count)| count <= bag.get(color).copied().unwrap_or(0)
             )
         )
     }
 
-    fn get_minimum_cubes_grab(&self) -> Grab {
-        let [red, green, blue] = self.grabs.iter().map(
-            |grab| [grab.red, grab.green, grab.blue]
-        ).reduce(
-            |min_colors, colors| {
-                let result = min_colors.into_iter().zip(colors.into_iter()).map(
-                    |(min_color, color)| if let (Some(min_color), Some(color)) = (min_color, color) {
-                        Some(max(min_color, color))
-                    } else if min_color.is_none() {
-                        color
+    pub fn get_minimum_cubes_grab(&self) -> Grab {
+        Grab { cubes: get_minimum_bag_for_grabs(self.grabs.iter()) }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grab {
+    cubes: BTreeMap<String, u32>
+}
+
+impl Grab {
+    pub fn new(cubes: BTreeMap<String, u32>) -> Result<Self, ParseGameError> {
+        if cubes.is_empty() {
+            Err(ParseGameError::new("Found no cubes", "grab", ""))
+        } else if let Some(color) = cubes.keys().find(
+            |color| color.is_empty() || color.contains(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        ) {
+            Err(ParseGameError::new("Colour names cannot be empty or contain whitespace, ',' or ';'", "colour", color))
+        } else {
+            Ok(Grab { cubes })
+        }
+    }
+
+    pub fn cubes(&self) -> &BTreeMap<String, u32> {
+        &self.cubes
+    }
+
+    pub fn get_power(&self) -> u32 {
+        self.cubes.values().product()
+    }
+
+    /* log-probability of drawing exactly this grab from the bag without replacement (multivariate hypergeometric) */
+    fn get_log_likelihood(&self, bag: &BTreeMap<String, u32>) -> f64 {
+        let (bag_total, grab_total) = (bag.values().sum::<u32>(), self.cubes.values().sum::<u32>());
+        if grab_total > bag_total || self.cubes.iter().any(|(color, &count)| count > bag.get(color).copied().unwrap_or(0)) {
+            return f64::NEG_INFINITY
+        }
+        self.cubes.iter().map(
+            |(color, &count)| log_binomial(bag.get(color).copied().unwrap_or(0), count)
+        ).sum::<f64>() - log_binomial(bag_total, grab_total)
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Game {}: {}",
+            self.id,
+            self.grabs.iter().map(|grab| grab.to_string()).collect::<Vec<String>>().join("; ")
+        )
+    }
+}
+
+impl FromStr for Game {
+    type Err = ParseGameError;
+
+    fn from_str(game_string: &str) -> Result<Self, Self::Err> {
+        if let Some((id_term, grabs_term)) = game_string.trim().split_once(':') {
+            let id = if let Some(id_str) = id_term.split_whitespace().collect::<Vec<&str>>().get(1) {
+                if let Ok(id) = id_str.parse::<u32>() {
+                    id
+                } else {
+                    return Err(ParseGameError::new("Could not parse id", "game", game_string))
+                }
+            } else {
+                return Err(ParseGameError::new("Could not parse id_str", "game", game_string))
+            };
+            let grabs = grabs_term.split(';').map(
+                |grab_term| grab_term.parse::<Grab>().map_err(|error| ParseGameError::new(&error.message, "game", game_string))
+            ).collect::<Result<Vec<Grab>, ParseGameError>>()?;
+            Game::new(id, grabs)
+        } else {
+            Err(ParseGameError::new("Could not parse id_term and grabs_term", "game", game_string))
+        }
+    }
+}
+
+impl fmt::Display for Grab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.cubes.iter().map(|(color, count)| format!("{} {}", count, color)).collect::<Vec<String>>().join(", ")
+        )
+    }
+}
+
+impl FromStr for Grab {
+    type Err = ParseGameError;
+
+    fn from_str(grab_string: &str) -> Result<Self, Self::Err> {
+        let mut cubes = BTreeMap::new();
+        for color_term in grab_string.trim().split(',') {
+            if let Some(&[count_str, color_str]) = color_term.split_whitespace().collect::<Vec<&str>>().get(0..2) {
+                if let Ok(count) = count_str.parse::<u32>() {
+                    let total = cubes.entry(String::from(color_str)).or_insert(0_u32);
+                    *total = if let Some(total) = total.checked_add(count) {
+                        total
                     } else {
-                        min_color
-                    }
-                ).collect::<Vec<Option<u32>>>();
-                [result[0], result[1], result[2]]
+                        return Err(ParseGameError::new("Total count of a colour exceeds u32", "grab", grab_string))
+                    };
+                } else {
+                    return Err(ParseGameError::new("Could not interpret a count_str as a number", "grab", grab_string))
+                }
+            } else {
+                return Err(ParseGameError::new("Could not parse a color_term", "grab", grab_string))
             }
-        ).unwrap_or([None, None, None]);
-        Grab {red, green, blue}
+        }
+        Grab::new(cubes)
     }
 }
 
-struct Grab {
-    red: Option<u32>,
-    green: Option<u32>,
-    blue: Option<u32>
+
+#[derive(Debug)]
+pub struct ParseGameError {
+    message: String
 }
 
-impl Grab {
-    fn get_power(&self) -> u32 {
-        self.red.unwrap_or(1) * self.green.unwrap_or(1) * self.blue.unwrap_or(1)
+impl ParseGameError {
+    fn new(message: &str, record_kind: &str, record: &str) -> Self {
+        ParseGameError { message: format!("{} for {} '{}'", message, record_kind, record) }
     }
 }
 
-enum Color {
-    Red(u32),
-    Green(u32),
-    Blue(u32)
+impl fmt::Display for ParseGameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
 }
 
-pub fn sum_valid_game_ids(games_string: String, rgb_limits: (u32, u32, u32)) -> u32 {
-    parse_games(games_string).iter().map(|game| if game.is_valid(rgb_limits) { game.id } else { 0 }).sum()
+
+pub fn sum_valid_game_ids(games_string: String, bag: &BTreeMap<String, u32>) -> u32 {
+    parse_games(games_string).iter().map(|game| if game.is_valid(bag) { game.id } else { 0 }).sum()
 }
 
 
@@ -63,53 +180,181 @@ pub fn sum_powers_of_minimum_grabs(games_string: String) -> u32 {
 }
 
 
-fn parse_games(games_string: String) -> Vec<Game> {
+pub fn get_minimum_bag(games: &[Game]) -> BTreeMap<String, u32> {
+    get_minimum_bag_for_grabs(games.iter().flat_map(|game| game.grabs.iter()))
+}
+
+
+pub fn get_games_consistent_with_bag<'a>(games: &'a [Game], bag: &BTreeMap<String, u32>) -> Vec<&'a Game> {
+    games.iter().filter(|game| game.is_valid(bag)).collect()
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BagEstimate {
+    pub bag: BTreeMap<String, u32>,
+    /* colours that ended up at max_cubes_per_color, where the likelihood may still be rising, so the count is a
+     * bound rather than an estimate
+     */
+    pub capped_colors: Vec<String>
+}
+
+
+/* Maximum-likelihood bag, assuming every grab is drawn without replacement from the same bag and put back
+ * afterwards. Starting from the minimum bag, it repeatedly picks the best count for one colour at a time, then
+ * for all colours moving together, up to max_cubes_per_color, until neither improves.
+ */
+pub fn estimate_bag_contents(games: &[Game], max_cubes_per_color: u32) -> BagEstimate {
+    let grabs = games.iter().flat_map(|game| game.grabs.iter()).collect::<Vec<&Grab>>();
+    let minimum_bag = get_minimum_bag_for_grabs(grabs.iter().copied());
+    let get_log_likelihood = |bag: &BTreeMap<String, u32>| grabs.iter().map(|grab| grab.get_log_likelihood(bag)).sum::<f64>();
+    let (mut bag, mut log_likelihood) = (minimum_bag.clone(), get_log_likelihood(&minimum_bag));
+    let mut improved = true;
+    while improved {
+        improved = false;
+        let mut consider = |candidate: &BTreeMap<String, u32>, bag: &mut BTreeMap<String, u32>| {
+            let candidate_log_likelihood = get_log_likelihood(candidate);
+            if candidate_log_likelihood > log_likelihood + 1e-9 {
+                (log_likelihood, improved) = (candidate_log_likelihood, true);
+                bag.clone_from(candidate);
+            }
+        };
+        for (color, &minimum_count) in minimum_bag.iter() {
+            let mut candidate = bag.clone();
+            for count in minimum_count..=max_cubes_per_color {
+                candidate.insert(color.clone(), count);
+                consider(&candidate, &mut bag);
+            }
+        }
+        /* the likelihood often only rises when every colour grows together, which single colours cannot see */
+        let start = bag.clone();
+        for step in 1..=start.values().map(|&count| max_cubes_per_color.saturating_sub(count)).min().unwrap_or(0) {
+            consider(&start.iter().map(|(color, &count)| (color.clone(), count + step)).collect(), &mut bag);
+        }
+        for step in 1..=start.iter().map(|(color, &count)| count - minimum_bag[color]).min().unwrap_or(0) {
+            consider(&start.iter().map(|(color, &count)| (color.clone(), count - step)).collect(), &mut bag);
+        }
+    }
+    let capped_colors = bag.iter().filter(|(_, &count)| count >= max_cubes_per_color).map(|(color, _)| color.clone()).collect();
+    BagEstimate { bag, capped_colors }
+}
+
+
+fn get_minimum_bag_for_grabs<'a>(grabs: impl Iterator<Item = &'a Grab>) -> BTreeMap<String, u32> {
+    let mut bag: BTreeMap<String, u32> = BTreeMap::new();
+    for grab in grabs {
+        for (color, &count) in grab.cubes.iter() {
+            let min_count = bag.entry(color.clone()).or_insert(0);
+            *min_count = max(*min_count, count);
+        }
+    }
+    bag
+}
+
+
+fn log_binomial(n: u32, k: u32) -> f64 {
+    (0..k).map(|i| ((n - i) as f64).ln() - ((i + 1) as f64).ln()).sum()
+}
+
+
+/* parses bag limits of the form 'red=12,green=13,blue=14' */
+pub fn parse_bag(bag_text: &str) -> BTreeMap<String, u32> {
+    bag_text.split(',').map(|term| term.trim()).filter(|term| !term.is_empty()).map(
+        |term| if let Some((color, count_str)) = term.split_once('=') {
+            if let Ok(count) = count_str.trim().parse::<u32>() {
+                (String::from(color.trim()), count)
+            } else {
+                panic!("Could not interpret '{}' as a number for bag term '{}'", count_str, term)
+            }
+        } else {
+            panic!("Could not parse color and count for bag term '{}'", term)
+        }
+    ).collect()
+}
+
+
+pub fn parse_games(games_string: String) -> Vec<Game> {
     games_string.split('\n').map(
-        |game_string| {
-            if let Some([id_term, grabs_term]) = game_string.trim().split(':').collect::<Vec<&str>>().get(0..2) {
-                let id = if let Some(id_str) = id_term.split_whitespace().collect::<Vec<&str>>().get(1) {
-                    if let Ok(id) = id_str.parse::<u32>() {
-                        id
-                    } else {
-                        panic!("Could not parse id for game '{}'", game_string)
-                    }
-                } else {
-                    panic!("Could not parse id_str for game '{}'", game_string)
-                };
-                let grabs = grabs_term.split(';').map(
-                    |colors_term| {
-                        let colors = colors_term.trim().split(',').map(
-                            |color_term| if let Some([count_str, color_str]) = color_term.split_whitespace().collect::<Vec<&str>>().get(0..2) {
-                                let count = if let Ok(count) = count_str.parse::<u32>() {
-                                    count
-                                } else {
-                                    panic!("Could not interpret a count_str as a number for game '{}'", game_string)
-                                };
-                                match *color_str {
-                                    "red" => Color::Red(count),
-                                    "green" => Color::Green(count),
-                                    "blue" => Color::Blue(count),
-                                    _ => panic!("Could not interpret a color_str as 'red', 'green', or 'blue' for game '{}'", game_string)
-                                }
-                            } else {
-                                panic!("Could not parse a color_term for game '{}'", game_string)
-                            }
-                        );
-                        let mut grab = Grab {red: None, green: None, blue: None};
+        |game_string| game_string.parse::<Game>().unwrap_or_else(|error| panic!("{}", error))
+    ).collect::<Vec<Game>>()
+}
+
+
+/* generates a synthetic game log; the same seed always gives the same games */
+pub fn generate_games(num_games: u32, colors: &[&str], max_grabs: u32, max_count: u32, seed: u64) -> Vec<Game> {
+    if colors.is_empty() {
+        panic!("Cannot generate grabs without any colours")
+    }
+    let mut state = seed.max(1);
+    let mut next_random = |bound: u32| {
+        /* xorshift64 */
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as u32
+    };
+    (1..=num_games).map(
+        |id| Game {
+            id,
+            grabs: (0..(1 + next_random(max_grabs.max(1)))).map(
+                |_| {
+                    let mut cubes = BTreeMap::new();
+                    while cubes.is_empty() {
                         for color in colors {
-                            match color {
-                                Color::Red(count) => grab.red = Some(grab.red.unwrap_or(0) + count),
-                                Color::Green(count) => grab.green = Some(grab.green.unwrap_or(0) + count),
-                                Color::Blue(count) => grab.blue = Some(grab.blue.unwrap_or(0) + count)
+                            if next_random(2) == 0 {
+                                cubes.insert(String::from(*color), 1 + next_random(max_count.max(1)));
                             }
                         }
-                        grab
                     }
-                ).collect::<Vec<Grab>>();
-                Game{id, grabs}
-            } else {
-                panic!("Could not parse id_term and grabs_term for game '{}'", game_string)
+                    Grab::new(cubes).unwrap_or_else(|error| panic!("{}", error))
+                }
+            ).collect()
+        }
+    ).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_games_round_trip() {
+        for seed in 1..=50 {
+            for colors in [&["red", "green", "blue"][..], &["yellow"], &["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"]] {
+                for game in generate_games(20, colors, 6, 30, seed) {
+                    assert_eq!(game.to_string().parse::<Game>().unwrap(), game);
+                }
             }
         }
-    ).collect::<Vec<Game>>()
+    }
+
+    #[test]
+    fn grabs_round_trip() {
+        let grab = Grab::new(BTreeMap::from([(String::from("red"), 0), (String::from("x:y"), 4294967295)])).unwrap();
+        assert_eq!(grab.to_string().parse::<Grab>().unwrap(), grab);
+    }
+
+    #[test]
+    fn parsing_normalises_records() {
+        let game = " Game 7:  2 red,1 blue ;3 red, 4 red ".parse::<Game>().unwrap();
+        assert_eq!(game.to_string(), "Game 7: 1 blue, 2 red; 7 red");
+    }
+
+    #[test]
+    fn records_that_cannot_round_trip_are_rejected() {
+        assert!(Game::new(1, Vec::new()).is_err());
+        assert!(Grab::new(BTreeMap::new()).is_err());
+        for color in ["", "dark red", "red,", "red;", "red\t"] {
+            assert!(Grab::new(BTreeMap::from([(String::from(color), 1)])).is_err());
+        }
+        for record in ["Game 1: ", "Game 1: 3 red; ", "Game 1: 3 red,", "Game x: 3 red", "Game 1: red 3", "Game 1: 4294967295 red, 1 red"] {
+            assert!(record.parse::<Game>().is_err());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "without any colours")]
+    fn generating_games_without_colours_panics() {
+        generate_games(1, &[], 3, 3, 1);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,305 @@
+use std::cmp::Ordering;
+use std::ops::{Add, Sub, Mul, Div, Neg};
+
+
+#[derive(Clone, Copy, Debug)]
+struct Rational {
+    numerator: i128,
+    denominator: i128
+}
+
+impl Rational {
+    fn new(numerator: i128, denominator: i128) -> Self {
+        if denominator == 0 {
+            panic!("Tried to create a rational with a denominator of 0")
+        }
+        let divisor = greatest_common_divisor(numerator, denominator) * denominator.signum();
+        Rational { numerator: numerator / divisor, denominator: denominator / divisor }
+    }
+
+    fn from_integer(value: i128) -> Self {
+        Rational { numerator: value, denominator: 1 }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.numerator == 0
+    }
+
+    fn to_integer(self) -> Option<i128> {
+        if self.denominator == 1 {
+            Some(self.numerator)
+        } else {
+            None
+        }
+    }
+}
+
+impl Add for Rational {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let divisor = greatest_common_divisor(self.denominator, other.denominator);
+        Rational::new(
+            self.numerator * (other.denominator / divisor) + other.numerator * (self.denominator / divisor),
+            self.denominator / divisor * other.denominator
+        )
+    }
+}
+
+impl Sub for Rational {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        /* cross-cancel before multiplying to keep intermediates small */
+        let (divisor_a, divisor_b) = (
+            greatest_common_divisor(self.numerator, other.denominator),
+            greatest_common_divisor(other.numerator, self.denominator)
+        );
+        Rational::new(
+            (self.numerator / divisor_a) * (other.numerator / divisor_b),
+            (self.denominator / divisor_b) * (other.denominator / divisor_a)
+        )
+    }
+}
+
+impl Div for Rational {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        self.mul(Rational::new(other.denominator, other.numerator))
+    }
+}
+
+impl Neg for Rational {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Rational { numerator: -self.numerator, denominator: self.denominator }
+    }
+}
+
+impl PartialEq for Rational {
+    fn eq(&self, other: &Self) -> bool {
+        self.numerator == other.numerator && self.denominator == other.denominator
+    }
+}
+
+impl Eq for Rational {
+
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        /* denominators are always positive, so cross-multiplying preserves order */
+        (self.numerator * other.denominator).cmp(&(other.numerator * self.denominator))
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Vector {
+    x: Rational,
+    y: Rational,
+    z: Rational
+}
+
+impl Vector {
+    fn from_integers(x: i128, y: i128, z: i128) -> Self {
+        Vector { x: Rational::from_integer(x), y: Rational::from_integer(y), z: Rational::from_integer(z) }
+    }
+
+    fn dot(&self, other: &Self) -> Rational {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    fn cross(&self, other: &Self) -> Self {
+        Vector {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x
+        }
+    }
+
+    fn scale(&self, factor: Rational) -> Self {
+        Vector { x: self.x * factor, y: self.y * factor, z: self.z * factor }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.x.is_zero() && self.y.is_zero() && self.z.is_zero()
+    }
+}
+
+impl Add for Vector {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Vector { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z }
+    }
+}
+
+impl Sub for Vector {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Vector { x: self.x - other.x, y: self.y - other.y, z: self.z - other.z }
+    }
+}
+
+
+#[derive(Clone, Copy)]
+struct Hailstone {
+    position: Vector,
+    velocity: Vector
+}
+
+impl Hailstone {
+    fn get_xy_intersection(&self, other: &Self) -> Option<(Rational, Rational)> {
+        /* solve self.position + t * self.velocity = other.position + s * other.velocity in x and y */
+        let determinant = self.velocity.x * other.velocity.y - self.velocity.y * other.velocity.x;
+        if determinant.is_zero() {
+            return None
+        }
+        let (delta_x, delta_y) = (other.position.x - self.position.x, other.position.y - self.position.y);
+        let t = (delta_x * other.velocity.y - delta_y * other.velocity.x) / determinant;
+        let s = (delta_x * self.velocity.y - delta_y * self.velocity.x) / determinant;
+        let zero = Rational::from_integer(0);
+        if t < zero || s < zero {
+            None
+        } else {
+            Some((self.position.x + self.velocity.x * t, self.position.y + self.velocity.y * t))
+        }
+    }
+
+    fn relative_to(&self, frame: &Self) -> Self {
+        Hailstone { position: self.position - frame.position, velocity: self.velocity - frame.velocity }
+    }
+
+    fn is_hit_by(&self, rock: &Self) -> bool {
+        (rock.position - self.position).cross(&(rock.velocity - self.velocity)).is_zero()
+    }
+}
+
+
+pub fn count_intersections_in_test_area(hailstones_text: String, test_area_min: i128, test_area_max: i128) -> usize {
+    let hailstones = parse_hailstones(hailstones_text);
+    let test_area = Rational::from_integer(test_area_min)..=Rational::from_integer(test_area_max);
+    hailstones.iter().enumerate().map(
+        |(i, hailstone)| hailstones[(i + 1)..].iter().filter(
+            |other| if let Some((x, y)) = hailstone.get_xy_intersection(other) {
+                test_area.contains(&x) && test_area.contains(&y)
+            } else {
+                false
+            }
+        ).count()
+    ).sum()
+}
+
+
+pub fn sum_rock_start_coordinates(hailstones_text: String) -> i128 {
+    let hailstones = parse_hailstones(hailstones_text);
+    let rock = find_rock_trajectory(&hailstones);
+    if !hailstones.iter().all(|hailstone| hailstone.is_hit_by(&rock)) {
+        panic!("Found a rock trajectory that does not hit every hailstone")
+    }
+    [rock.position.x, rock.position.y, rock.position.z].into_iter().map(
+        |coordinate| if let Some(coordinate) = coordinate.to_integer() {
+            coordinate
+        } else {
+            panic!("Rock starts at a non-integer position")
+        }
+    ).sum()
+}
+
+
+fn find_rock_trajectory(hailstones: &[Hailstone]) -> Hailstone {
+    /* In the frame of the first hailstone, that hailstone sits still at the origin, so the rock
+     * has to pass through the origin. Together with any other hailstone's path, the origin spans
+     * a plane the rock must lie in, and the times at which further hailstones cross that plane
+     * give two points on the rock's path.
+     */
+    let (frame, others) = if let Some((frame, others)) = hailstones.split_first() {
+        (frame, others.iter().map(|hailstone| hailstone.relative_to(frame)).collect::<Vec<Hailstone>>())
+    } else {
+        panic!("Cannot find a rock trajectory without any hailstones")
+    };
+    let normal = if let Some(normal) = others.iter().map(
+        |hailstone| hailstone.position.cross(&hailstone.velocity)
+    ).find(|normal| !normal.is_zero()) {
+        normal
+    } else {
+        panic!("Every hailstone collides with the first, so the rock trajectory is undetermined")
+    };
+    let crossings = others.iter().filter_map(
+        |hailstone| {
+            let speed_towards_plane = hailstone.velocity.dot(&normal);
+            if speed_towards_plane.is_zero() {
+                None
+            } else {
+                let time = -hailstone.position.dot(&normal) / speed_towards_plane;
+                Some((time, hailstone.position + hailstone.velocity.scale(time)))
+            }
+        }
+    ).collect::<Vec<(Rational, Vector)>>();
+    let ((time_a, point_a), (time_b, point_b)) = if let Some((first, second)) = crossings.first().and_then(
+        |first| crossings.iter().find(|(time, _)| *time != first.0).map(|second| (*first, *second))
+    ) {
+        (first, second)
+    } else {
+        panic!("Could not find two hailstones crossing the rock's plane at different times")
+    };
+    let velocity = (point_b - point_a).scale(Rational::from_integer(1) / (time_b - time_a));
+    let position = point_a - velocity.scale(time_a);
+    Hailstone {
+        position: position + frame.position,
+        velocity: velocity + frame.velocity
+    }
+}
+
+
+fn greatest_common_divisor(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        let last_b = b;
+        b = a % b;
+        a = last_b;
+    }
+    if a == 0 { 1 } else { a }
+}
+
+
+fn parse_hailstones(hailstones_text: String) -> Vec<Hailstone> {
+    hailstones_text.split('\n').map(|line| line.trim()).filter(|line| !line.is_empty()).map(
+        |line| if let Some([position_term, velocity_term]) = line.split('@').collect::<Vec<&str>>().get(0..2) {
+            let [position, velocity] = [position_term, velocity_term].map(
+                |term| if let Some(&[x, y, z]) = term.split(',').map(
+                    |num_str| if let Ok(num) = num_str.trim().parse::<i128>() {
+                        num
+                    } else {
+                        panic!("Could not parse '{}' as a number for hailstone '{}'", num_str.trim(), line)
+                    }
+                ).collect::<Vec<i128>>().get(0..3) {
+                    Vector::from_integers(x, y, z)
+                } else {
+                    panic!("Could not find three coordinates for hailstone '{}'", line)
+                }
+            );
+            Hailstone { position, velocity }
+        } else {
+            panic!("Could not parse position_term and velocity_term for hailstone '{}'", line)
+        }
+    ).collect()
+}
\ No newline at end of file
@@ -0,0 +1,33 @@
+use crate::utilities::UndirectedGraph;
+
+
+const WIRES_TO_CUT: u64 = 3;
+
+
+pub fn multiply_group_sizes_after_cut(wiring_text: String) -> usize {
+    let graph = parse_wiring(wiring_text);
+    if let Some((cut_weight, group)) = graph.find_minimum_cut() {
+        if cut_weight != WIRES_TO_CUT {
+            panic!("Expected a minimum cut of {} wires but found one of {}", WIRES_TO_CUT, cut_weight)
+        }
+        group.len() * (graph.num_nodes() - group.len())
+    } else {
+        panic!("Could not find a cut for a wiring diagram with fewer than two components")
+    }
+}
+
+
+fn parse_wiring(wiring_text: String) -> UndirectedGraph {
+    let mut graph = UndirectedGraph::new();
+    for line in wiring_text.split('\n').map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        if let Some([component, connections_term]) = line.split(':').map(|term| term.trim()).collect::<Vec<&str>>().get(0..2) {
+            graph.add_node(component);
+            for connection in connections_term.split_whitespace() {
+                graph.add_edge(component, connection);
+            }
+        } else {
+            panic!("Could not parse component and connections_term for line '{}'", line)
+        }
+    }
+    graph
+}
\ No newline at end of file
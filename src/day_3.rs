@@ -1,139 +1,332 @@
-enum SchematicTerm {
-    Number { value: u32, start_pos: usize, length: usize },
-    Symbol { value: char, pos: usize }
+use std::fmt;
+use crate::utilities::Grid;
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchematicNumber {
+    pub value: u64,
+    pub row: usize,
+    pub start_col: usize,
+    pub length: usize
 }
 
 
-struct SchematicLine {
-    terms: Vec<SchematicTerm>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchematicSymbol {
+    pub value: char,
+    pub row: usize,
+    pub col: usize
 }
 
-impl SchematicLine {
-    fn is_range_part_number(&self, start_pos: usize, length: usize) -> bool {
-        self.terms.iter().any(
-            |term| if let &SchematicTerm::Symbol { value: _, pos } = term {
-                is_pos_adjacent_to_range(pos, start_pos, length)
-            } else {
-                false
-            }
-        )
+
+#[derive(Clone, Copy)]
+pub enum NeighborCount {
+    Exactly(usize),
+    AtLeast(usize),
+    AtMost(usize),
+    Between(usize, usize)
+}
+
+impl NeighborCount {
+    fn allows(&self, count: usize) -> bool {
+        match *self {
+            NeighborCount::Exactly(required) => count == required,
+            NeighborCount::AtLeast(min) => count >= min,
+            NeighborCount::AtMost(max) => count <= max,
+            NeighborCount::Between(min, max) => (min..=max).contains(&count)
+        }
     }
+}
 
-    fn get_numbers_bordering_symbol(&self, pos: usize) -> Vec<u32> {
-        self.terms.iter().filter_map(
-            |term| if let &SchematicTerm::Number { value, start_pos, length} = term {
-                if is_pos_adjacent_to_range(pos, start_pos, length) {
-                    Some(value)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        ).collect()
+
+#[derive(Clone, Copy)]
+pub enum Combine {
+    Product,
+    Sum,
+    Custom(fn(&[u64]) -> u64)
+}
+
+impl Combine {
+    fn apply(&self, values: &[u64]) -> u64 {
+        match self {
+            Combine::Product => values.iter().try_fold(1_u64, |product, &value| product.checked_mul(value)).unwrap_or_else(
+                || panic!("Product of {:?} does not fit in a u64", values)
+            ),
+            Combine::Sum => checked_sum(values.iter().copied()),
+            Combine::Custom(combine) => combine(values)
+        }
     }
 }
 
 
-pub fn sum_part_numbers(schematic: String) -> u32 {
-    let schematic_lines: Vec<SchematicLine> = schematic.split('\n').map(|line_text| parse_schematic_line(line_text)).collect();
-    schematic_lines.iter().enumerate().map(
-        |(i, line)| {
-            let (last, next) = (
-                if i > 0 { schematic_lines.get(i - 1) } else { None },
-                schematic_lines.get(i + 1)
-            );
-            line.terms.iter().map(
-                |term| if let &SchematicTerm::Number { value, start_pos, length} = term {
-                    if [last, Some(line), next].iter().any(
-                        |line| if let Some(line) = line {
-                            line.is_range_part_number(start_pos, length)
-                        } else {
-                            false
-                        }
-                    ) {
-                        value
-                    } else {
-                        0
-                    }
+/* which symbols count as gears, how many numbers they need, and how those numbers make a ratio */
+pub struct GearRule {
+    pub symbols: Vec<char>,
+    pub neighbor_count: NeighborCount,
+    pub combine: Combine
+}
+
+impl GearRule {
+    pub fn standard() -> Self {
+        GearRule { symbols: vec!['*'], neighbor_count: NeighborCount::Exactly(2), combine: Combine::Product }
+    }
+}
+
+
+pub struct Schematic {
+    grid: Grid<char>,
+    numbers: Vec<SchematicNumber>,
+    symbols: Vec<SchematicSymbol>,
+    number_indices: Grid<Option<usize>>,
+    symbol_indices: Grid<Option<usize>>
+}
+
+impl Schematic {
+    fn is_symbol(c: char) -> bool {
+        c != '.' && !c.is_ascii_digit() && !c.is_whitespace()
+    }
+
+    pub fn numbers(&self) -> &[SchematicNumber] {
+        &self.numbers
+    }
+
+    pub fn symbols(&self) -> &[SchematicSymbol] {
+        &self.symbols
+    }
+
+    pub fn is_part_number(&self, number: &SchematicNumber) -> bool {
+        !self.get_symbols_touching_number(number).is_empty()
+    }
+
+    pub fn get_symbols_touching_number(&self, number: &SchematicNumber) -> Vec<&SchematicSymbol> {
+        let mut symbol_indices = (number.start_col..(number.start_col + number.length)).flat_map(
+            |col| self.grid.neighbors_8(number.row, col)
+        ).filter_map(|pos| self.symbol_indices[pos]).collect::<Vec<usize>>();
+        symbol_indices.sort();
+        symbol_indices.dedup();
+        symbol_indices.into_iter().map(|i| &self.symbols[i]).collect()
+    }
+
+    pub fn get_numbers_touching_symbol(&self, symbol: &SchematicSymbol) -> Vec<&SchematicNumber> {
+        let mut number_indices = self.grid.neighbors_8(symbol.row, symbol.col).filter_map(
+            |pos| self.number_indices[pos]
+        ).collect::<Vec<usize>>();
+        number_indices.sort();
+        number_indices.dedup();
+        number_indices.into_iter().map(|i| &self.numbers[i]).collect()
+    }
+
+    /* e.g. every number touching a '#' */
+    pub fn find_numbers_touching(&self, symbol_value: char) -> Vec<&SchematicNumber> {
+        self.numbers.iter().filter(
+            |number| self.get_symbols_touching_number(number).iter().any(|symbol| symbol.value == symbol_value)
+        ).collect()
+    }
+
+    /* e.g. every '*' with exactly 3 neighbouring numbers */
+    pub fn find_symbols_with_neighbor_count(&self, symbol_value: char, neighbor_count: usize) -> Vec<&SchematicSymbol> {
+        self.symbols.iter().filter(
+            |symbol| symbol.value == symbol_value && self.get_numbers_touching_symbol(symbol).len() == neighbor_count
+        ).collect()
+    }
+}
+
+impl fmt::Display for Schematic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Numbers:")?;
+        for number in self.numbers.iter() {
+            let symbols = self.get_symbols_touching_number(number);
+            writeln!(
+                f,
+                "  {} at ({}, {}): {}",
+                number.value,
+                number.row,
+                number.start_col,
+                if symbols.is_empty() {
+                    String::from("not a part")
                 } else {
-                    0
+                    format!(
+                        "part, touches {}",
+                        symbols.iter().map(
+                            |symbol| format!("'{}' ({}, {})", symbol.value, symbol.row, symbol.col)
+                        ).collect::<Vec<String>>().join(", ")
+                    )
                 }
-            ).sum::<u32>()
+            )?;
+        }
+        writeln!(f, "Symbols:")?;
+        for symbol in self.symbols.iter() {
+            let numbers = self.get_numbers_touching_symbol(symbol);
+            writeln!(
+                f,
+                "  '{}' at ({}, {}): {}",
+                symbol.value,
+                symbol.row,
+                symbol.col,
+                if numbers.is_empty() {
+                    String::from("no numbers")
+                } else {
+                    numbers.iter().map(
+                        |number| format!("{} ({}, {})", number.value, number.row, number.start_col)
+                    ).collect::<Vec<String>>().join(", ")
+                }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+
+pub fn sum_part_numbers(schematic_text: String) -> u64 {
+    let schematic = parse_schematic(schematic_text);
+    checked_sum(
+        schematic.numbers.iter().filter(
+            |number| schematic.is_part_number(number)
+        ).map(|number| number.value)
+    )
+}
+
+
+pub fn sum_gear_ratios(schematic_text: String, rule: &GearRule) -> u64 {
+    let schematic = parse_schematic(schematic_text);
+    checked_sum(schematic.symbols.iter().filter(
+        |symbol| rule.symbols.contains(&symbol.value)
+    ).map(
+        |symbol| {
+            let values = schematic.get_numbers_touching_symbol(symbol).iter().map(|number| number.value).collect::<Vec<u64>>();
+            /* a symbol with no numbers has no ratio, even under a rule that allows 0 neighbours */
+            if !values.is_empty() && rule.neighbor_count.allows(values.len()) {
+                rule.combine.apply(&values)
+            } else {
+                0
+            }
         }
-    ).sum()
+    ))
 }
 
 
-pub fn sum_gear_ratios(schematic: String) -> u32 {
-    let schematic_lines: Vec<SchematicLine> = schematic.split('\n').map(|line_text| parse_schematic_line(line_text)).collect();
-    schematic_lines.iter().enumerate().map(
-        |(i, line)| {
-            let (last, next) = (
-                if i > 0 { schematic_lines.get(i - 1) } else { None },
-                schematic_lines.get(i + 1)
-            );
-            line.terms.iter().map(
-                |term| if let &SchematicTerm::Symbol { value: '*', pos} = term {
-                    let neighboring_numbers: Vec<u32> = [last, Some(line), next].iter().flat_map(
-                        |line| if let Some(line) = line {
-                            line.get_numbers_bordering_symbol(pos)
+fn checked_sum(values: impl Iterator<Item = u64>) -> u64 {
+    values.fold(
+        0_u64,
+        |sum, value| sum.checked_add(value).unwrap_or_else(|| panic!("Sum exceeds u64 after adding {}", value))
+    )
+}
+
+
+pub fn parse_schematic(schematic_text: String) -> Schematic {
+    /* short lines are padded with '.' so every cell keeps its true column */
+    let grid = Grid::from_text_padded(&schematic_text, '.', |c| c);
+    let (mut numbers, mut symbols) = (Vec::new(), Vec::new());
+    let mut number_indices = Grid::filled(grid.num_rows(), grid.num_cols(), None);
+    let mut symbol_indices = Grid::filled(grid.num_rows(), grid.num_cols(), None);
+    for (row, cells) in grid.rows().enumerate() {
+        let mut current_number: Option<SchematicNumber> = None;
+        for (col, &c) in cells.iter().enumerate() {
+            if let Some(digit) = c.to_digit(10) {
+                current_number = Some(
+                    if let Some(SchematicNumber { value, row, start_col, length }) = current_number {
+                        if let Some(value) = value.checked_mul(10).and_then(|value| value.checked_add(digit as u64)) {
+                            SchematicNumber { value, row, start_col, length: length + 1 }
                         } else {
-                            Vec::new()
+                            panic!("Number starting at ({}, {}) does not fit in a u64", row, start_col)
                         }
-                    ).collect();
-                    if neighboring_numbers.len() == 2 {
-                        neighboring_numbers.get(0).unwrap() * neighboring_numbers.get(1).unwrap()
                     } else {
-                        0
+                        SchematicNumber { value: digit as u64, row, start_col: col, length: 1 }
                     }
-                } else {
-                    0
+                );
+                number_indices[(row, col)] = Some(numbers.len());
+            } else {
+                if let Some(number) = current_number.take() {
+                    numbers.push(number);
+                }
+                if Schematic::is_symbol(c) {
+                    symbol_indices[(row, col)] = Some(symbols.len());
+                    symbols.push(SchematicSymbol { value: c, row, col });
                 }
-            ).sum::<u32>()
+            }
         }
-    ).sum()
+        if let Some(number) = current_number {
+            numbers.push(number);
+        }
+    }
+    Schematic { grid, numbers, symbols, number_indices, symbol_indices }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn is_pos_adjacent_to_range(pos: usize, start_pos: usize, length: usize) -> bool {
-    pos >= if start_pos == 0 { start_pos } else { start_pos - 1 } && pos <= start_pos + length
-}
+    fn find_number(schematic: &Schematic, value: u64) -> &SchematicNumber {
+        schematic.numbers().iter().find(|number| number.value == value).unwrap()
+    }
 
+    #[test]
+    fn numbers_at_the_edge_columns() {
+        let schematic = parse_schematic(String::from("12....34\n#......$"));
+        assert_eq!(find_number(&schematic, 12), &SchematicNumber { value: 12, row: 0, start_col: 0, length: 2 });
+        assert_eq!(find_number(&schematic, 34), &SchematicNumber { value: 34, row: 0, start_col: 6, length: 2 });
+        assert_eq!(sum_part_numbers(String::from("12....34\n#......$")), 46);
+        assert_eq!(sum_part_numbers(String::from("12....34\n...##...")), 0);
+    }
 
-fn parse_schematic_line(line_text: &str) -> SchematicLine {
-    let mut terms = Vec::new();
-    let mut current_number: Option<SchematicTerm> = None;
-    for (i, c) in line_text.trim().char_indices() {
-        if c.is_digit(10) {
-            let digit = c.to_digit(10).unwrap();
-            current_number = Some(
-                if let Some(SchematicTerm::Number { value, start_pos, length }) = current_number {
-                    SchematicTerm::Number {
-                        value: value * 10 + digit,
-                        start_pos: start_pos,
-                        length: length + 1
-                    }
-                } else {
-                    SchematicTerm::Number {
-                        value: digit,
-                        start_pos: i,
-                        length: 1
-                    }
-                }
-            )
-        } else {
-            if let Some(number) = current_number {
-                terms.push(number);
-            }
-            current_number = None;
-            if c != '.' {
-                terms.push(SchematicTerm::Symbol { value: c, pos: i })
+    #[test]
+    fn symbols_on_the_first_and_last_rows() {
+        let text = String::from("*..+\n5..6\n7..8\n.-..");
+        let schematic = parse_schematic(text.clone());
+        assert_eq!(
+            schematic.symbols(),
+            &[
+                SchematicSymbol { value: '*', row: 0, col: 0 },
+                SchematicSymbol { value: '+', row: 0, col: 3 },
+                SchematicSymbol { value: '-', row: 3, col: 1 }
+            ]
+        );
+        assert_eq!(sum_part_numbers(text.clone()), 5 + 6 + 7);
+        assert_eq!(sum_gear_ratios(text, &GearRule::standard()), 0);
+    }
+
+    #[test]
+    fn short_lines_are_padded() {
+        let text = String::from("467..114\n...*\n..35..633\n#");
+        let schematic = parse_schematic(text.clone());
+        assert_eq!(find_number(&schematic, 633), &SchematicNumber { value: 633, row: 2, start_col: 6, length: 3 });
+        assert_eq!(schematic.symbols()[1], SchematicSymbol { value: '#', row: 3, col: 0 });
+        assert_eq!(sum_part_numbers(text.clone()), 467 + 35);
+        assert_eq!(sum_gear_ratios(text, &GearRule::standard()), 467 * 35);
+    }
+
+    #[test]
+    fn leading_spaces_keep_their_columns() {
+        let text = String::from("  12\n    *\n 7");
+        let schematic = parse_schematic(text.clone());
+        assert_eq!(find_number(&schematic, 12), &SchematicNumber { value: 12, row: 0, start_col: 2, length: 2 });
+        assert_eq!(schematic.symbols(), &[SchematicSymbol { value: '*', row: 1, col: 4 }]);
+        assert_eq!(sum_part_numbers(text), 12);
+    }
+
+    #[test]
+    fn values_beyond_u32() {
+        let text = String::from("12345678901234*2");
+        assert_eq!(find_number(&parse_schematic(text.clone()), 12345678901234).length, 14);
+        assert_eq!(sum_part_numbers(text.clone()), 12345678901236);
+        assert_eq!(sum_gear_ratios(text, &GearRule::standard()), 24691357802468);
+    }
+
+    #[test]
+    fn symbols_without_neighbors_have_no_ratio() {
+        for neighbor_count in [NeighborCount::AtMost(2), NeighborCount::Exactly(0), NeighborCount::Between(0, 3)] {
+            for combine in [Combine::Product, Combine::Sum] {
+                let rule = GearRule { symbols: vec!['*'], neighbor_count, combine };
+                assert_eq!(sum_gear_ratios(String::from("..*.."), &rule), 0);
             }
         }
+        let rule = GearRule { symbols: vec!['*'], neighbor_count: NeighborCount::AtMost(2), combine: Combine::Product };
+        assert_eq!(sum_gear_ratios(String::from("*......\n..5*6*.\n......*"), &rule), 30 + 6);
     }
-    if let Some(number) = current_number {
-        terms.push(number);
+
+    #[test]
+    #[should_panic(expected = "does not fit in a u64")]
+    fn values_beyond_u64_panic() {
+        parse_schematic(String::from("123456789012345678901*"));
     }
-    SchematicLine { terms }
 }
\ No newline at end of file
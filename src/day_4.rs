@@ -1,70 +1,166 @@
-use std::collections::HashMap;
+use std::cmp::min;
+
+/* winning numbers up to this bound are kept in a bitset, larger ones in a sorted list */
+const BITSET_LIMIT: u32 = 1 << 16;
+
+
+enum NumberSet {
+    Bits(Vec<u64>),
+    Sorted(Vec<u32>)
+}
+
+impl NumberSet {
+    fn new(numbers: &[u32]) -> Self {
+        if numbers.iter().all(|&number| number < BITSET_LIMIT) {
+            let mut bits = vec![0_u64; (numbers.iter().max().map_or(0, |&max| max as usize) >> 6) + 1];
+            for &number in numbers {
+                bits[number as usize >> 6] |= 1 << (number & 63);
+            }
+            NumberSet::Bits(bits)
+        } else {
+            let mut sorted = numbers.to_vec();
+            sorted.sort_unstable();
+            NumberSet::Sorted(sorted)
+        }
+    }
+
+    fn contains(&self, number: u32) -> bool {
+        match self {
+            NumberSet::Bits(bits) => bits.get(number as usize >> 6).is_some_and(|word| word & (1 << (number & 63)) != 0),
+            NumberSet::Sorted(sorted) => sorted.binary_search(&number).is_ok()
+        }
+    }
+}
+
 
 struct Card {
-    winning_numbers: Vec<u32>,
+    id: usize,
+    winning_numbers: NumberSet,
     held_numbers: Vec<u32>
 }
 
 
 impl Card {
-    fn get_num_matches(&self) -> u32 {
-        self.held_numbers.iter().map(|number| if self.winning_numbers.contains(number) { 1 } else { 0 }).sum::<u32>()
+    fn get_num_matches(&self) -> usize {
+        self.held_numbers.iter().filter(|&&number| self.winning_numbers.contains(number)).count()
     }
+}
 
-    fn get_value(&self) -> u32 {
-        let power = self.get_num_matches();
-        if power > 0 {
-            2_u32.pow(power - 1)
-        } else {
-            0
+
+#[derive(Clone, Copy)]
+pub enum ScoringRule {
+    /* 1, 2, 4, 8, ... */
+    Doubling,
+    /* 1, 2, 3, 4, ... */
+    Linear,
+    /* 1, 1, 2, 3, 5, ... */
+    Fibonacci
+}
+
+impl ScoringRule {
+    pub fn score(&self, num_matches: usize) -> u64 {
+        if num_matches == 0 {
+            return 0
         }
+        let score = match self {
+            ScoringRule::Doubling => 2_u64.checked_pow(num_matches as u32 - 1),
+            ScoringRule::Linear => Some(num_matches as u64),
+            ScoringRule::Fibonacci => (1..num_matches).try_fold(
+                (0_u64, 1_u64),
+                |(previous, current), _| current.checked_add(previous).map(|next| (current, next))
+            ).map(|(_, current)| current)
+        };
+        score.unwrap_or_else(|| panic!("A card with {} matches is worth more than fits in a u64", num_matches))
     }
 }
 
 
-pub fn sum_cards(cards_text: String) -> u32 {
-    cards_text.split('\n').map(|card_text| parse_card(card_text.trim()).get_value()).sum()
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CardCopies {
+    pub card_id: usize,
+    pub copies: u64,
+    /* (card id, copies won from that card), not counting the original */
+    pub won_from: Vec<(usize, u64)>
 }
 
 
-pub fn count_cards(cards_text: String) -> u32 {
-    let cards = cards_text.split('\n').map(|card_text| parse_card(card_text.trim())).collect::<Vec<Card>>();
-    let mut cache = HashMap::new();
-    (0..cards.len()).map(|i| count_cards_from_card(&cards, i, &mut cache)).sum()
+pub fn sum_cards(cards_text: String, rule: &ScoringRule) -> u64 {
+    parse_cards(cards_text).iter().map(|card| rule.score(card.get_num_matches())).fold(
+        0,
+        |total, value| total.checked_add(value).unwrap_or_else(|| panic!("Total card value exceeds u64"))
+    )
 }
 
 
-fn count_cards_from_card(cards: &Vec<Card>, start_index: usize, cache: &mut HashMap<usize, u32>) -> u32 {
-    if let Some(&count) = cache.get(&start_index) {
-        count
-    }
-     else if let Some(card) = cards.get(start_index) {
-        let count = 1 + (0..(card.get_num_matches() as usize)).map(|i| count_cards_from_card(cards, start_index + i + 1, cache)).sum::<u32>();
-        cache.insert(start_index, count);
-        count
-    } else {
-        0
+pub fn count_cards(cards_text: String) -> u64 {
+    get_copy_ledger(cards_text).iter().fold(
+        0,
+        |total, card| total.checked_add(card.copies).unwrap_or_else(|| panic!("Total number of cards exceeds u64"))
+    )
+}
+
+
+pub fn get_copy_ledger(cards_text: String) -> Vec<CardCopies> {
+    let cards = parse_cards(cards_text);
+    let mut ledger = cards.iter().map(
+        |card| CardCopies { card_id: card.id, copies: 1, won_from: Vec::new() }
+    ).collect::<Vec<CardCopies>>();
+    /* every copy of a card wins one copy of each of the following cards it matches, so push copies forward in order */
+    for (i, card) in cards.iter().enumerate() {
+        let (card_id, copies) = (ledger[i].card_id, ledger[i].copies);
+        for entry in ledger[(i + 1)..min(i + 1 + card.get_num_matches(), cards.len())].iter_mut() {
+            entry.copies = entry.copies.checked_add(copies).unwrap_or_else(
+                || panic!("Number of copies of card {} exceeds u64", entry.card_id)
+            );
+            entry.won_from.push((card_id, copies));
+        }
     }
+    ledger
 }
 
 
+/* card ids have to run consecutively, since a card wins copies of the cards directly after it */
+fn parse_cards(cards_text: String) -> Vec<Card> {
+    let cards = cards_text.split('\n').map(|card_text| parse_card(card_text.trim())).collect::<Vec<Card>>();
+    for pair in cards.windows(2) {
+        if pair[1].id != pair[0].id + 1 {
+            panic!("Card {} is followed by card {}, expected card {}", pair[0].id, pair[1].id, pair[0].id + 1)
+        }
+    }
+    cards
+}
+
 
 fn parse_card(card_text: &str) -> Card {
-    if let Some(numbers_term) = card_text.split(':').map(|term| term.trim()).last() {
+    if let Some((id_term, numbers_term)) = card_text.split_once(':') {
+        let id = if let Some(Ok(id)) = id_term.split_whitespace().nth(1).map(|id_str| id_str.parse::<usize>()) {
+            id
+        } else {
+            panic!("Could not parse id for card '{}'", card_text)
+        };
         if let Some([winning_numbers_term, held_numbers_term]) = numbers_term.split('|').map(|term| term.trim()).collect::<Vec<&str>>().get(0..2) {
             let [winning_numbers, held_numbers] = [
                 winning_numbers_term, held_numbers_term
             ].map(
-                |numbers_term| numbers_term.split_whitespace().map(
-                    |number_term| if let Ok(number) = number_term.parse::<u32>() {
-                        number
-                    } else {
-                        panic!("Encountered a term that could not be parsed as a number for card '{}'", card_text)
+                |numbers_term| {
+                    let numbers = numbers_term.split_whitespace().map(
+                        |number_term| if let Ok(number) = number_term.parse::<u32>() {
+                            number
+                        } else {
+                            panic!("Encountered a term that could not be parsed as a number for card '{}'", card_text)
+                        }
+                    ).collect::<Vec<u32>>();
+                    let mut sorted = numbers.clone();
+                    sorted.sort_unstable();
+                    if let Some(pair) = sorted.windows(2).find(|pair| pair[0] == pair[1]) {
+                        panic!("Number {} appears more than once in '{}' for card '{}'", pair[0], numbers_term, card_text)
                     }
-                ).collect::<Vec<u32>>()
+                    numbers
+                }
             );
             Card {
-                winning_numbers,
+                id,
+                winning_numbers: NumberSet::new(&winning_numbers),
                 held_numbers
             }
         } else {
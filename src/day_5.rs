@@ -1,223 +1,480 @@
 use std::cmp::{min, max};
+use std::fmt;
 use std::ops::Range;
-use std::collections::HashMap;
-use std::str::FromStr;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use crate::utilities::IntervalSet;
+
+const DOMAIN_END: i128 = u64::MAX as i128 + 1;
+
 
 struct AlmanacMap {
-    dest_cat: String,
     src_range: Range<u64>,
     dest_range: Range<u64>
 }
 
-impl AlmanacMap {
-    fn map_input(&self, src_num: u64) -> Option<u64> {
-        if self.src_range.contains(&src_num) {
-            Some(src_num - self.src_range.start + self.dest_range.start)
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub start: u64,
+    pub offset: i128
+}
+
+
+/* A function on u64 that adds a constant offset within each segment. Segments are sorted by start,
+ * the first one starts at 0 and each runs until the next one starts, so every u64 is covered.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PiecewiseLinearMap {
+    segments: Vec<Segment>
+}
+
+impl PiecewiseLinearMap {
+    fn identity() -> Self {
+        PiecewiseLinearMap { segments: vec![Segment { start: 0, offset: 0 }] }
+    }
+
+    fn from_almanac_maps(cat_maps: &[AlmanacMap]) -> Self {
+        /* earlier maps take precedence, so they are laid over the later ones */
+        cat_maps.iter().rev().fold(
+            Self::identity(),
+            |map, cat_map| map.with_segment(
+                &cat_map.src_range,
+                cat_map.dest_range.start as i128 - cat_map.src_range.start as i128
+            )
+        )
+    }
+
+    fn from_pieces(pieces: impl IntoIterator<Item = Segment>) -> Self {
+        let mut segments: Vec<Segment> = Vec::new();
+        for piece in pieces {
+            if segments.last().is_some_and(|last| last.start == piece.start) {
+                segments.pop();
+            }
+            if segments.last().is_none_or(|last| last.offset != piece.offset) {
+                segments.push(piece);
+            }
+        }
+        PiecewiseLinearMap { segments }
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    fn get_segment_end(&self, i: usize) -> i128 {
+        self.segments.get(i + 1).map_or(DOMAIN_END, |segment| segment.start as i128)
+    }
+
+    fn find_segment(&self, value: i128) -> usize {
+        self.segments.partition_point(|segment| (segment.start as i128) <= value) - 1
+    }
+
+    fn with_segment(&self, range: &Range<u64>, offset: i128) -> Self {
+        if range.start >= range.end {
+            return self.clone()
+        }
+        let offset_after = self.segments[self.find_segment(range.end as i128)].offset;
+        Self::from_pieces(
+            self.segments.iter().filter(|segment| segment.start < range.start).copied()
+                .chain([Segment { start: range.start, offset }, Segment { start: range.end, offset: offset_after }])
+                .chain(self.segments.iter().filter(|segment| segment.start > range.end).copied())
+        )
+    }
+
+    pub fn apply(&self, value: u64) -> u64 {
+        let mapped = value as i128 + self.segments[self.find_segment(value as i128)].offset;
+        if let Ok(mapped) = u64::try_from(mapped) {
+            mapped
         } else {
-            None
+            panic!("Mapping {} gives {}, which is outside of u64", value, mapped)
         }
     }
 
-    fn map_input_range(&self, src_range: &Range<u64>) -> Option<(Range<u64>, Range<u64>)> {
-        if src_range.start <= self.src_range.start {
-            if src_range.end <= self.src_range.start {
-                /* <src_range>................
-                 * ...........<self.src_range>
-                 */
-                None
-            } else {
-                if src_range.end <= self.src_range.end {
-                    /* ...<src_range>.........
-                     * .......<self.src_range>
-                     */
-                    Some((
-                        self.src_range.start..src_range.end,
-                        self.dest_range.start..(src_range.end - self.src_range.start + self.dest_range.start)
-                    ))
+    pub fn apply_range(&self, range: &Range<u64>) -> IntervalSet<u64> {
+        if range.start >= range.end {
+            return IntervalSet::new()
+        }
+        let (start, end) = (range.start as i128, range.end as i128);
+        (self.find_segment(start)..self.find_segment(end - 1) + 1).map(
+            |i| {
+                let offset = self.segments[i].offset;
+                let (piece_start, piece_end) = (max(start, self.segments[i].start as i128), min(end, self.get_segment_end(i)));
+                if let (Ok(mapped_start), Ok(mapped_end)) = (u64::try_from(piece_start + offset), u64::try_from(piece_end + offset)) {
+                    mapped_start..mapped_end
                 } else {
-                    /* ..<....src_range....>..
-                     * ...<self.src_range>....
-                     */
-                    Some((
-                        self.src_range.start..self.src_range.end,
-                        self.dest_range.start..self.dest_range.end
-                    ))
+                    panic!("Mapping {}..{} leaves the range of u64", piece_start, piece_end)
                 }
             }
-        } else {
-            if src_range.start >= self.src_range.end {
-                /* ................<src_range>
-                 * <self.src_range>...........
-                 */
-                None
-            } else {
-                if src_range.end <= self.src_range.end {
-                    /* ..<src_range>..........
-                     * <self.src_range>.......
-                     */
-                    Some((
-                        src_range.start..src_range.end,
-                        (src_range.start - self.src_range.start + self.dest_range.start)..(src_range.end - self.src_range.start + self.dest_range.start)
-                    ))
+        ).collect()
+    }
+
+    /* every input that maps into range; a map need not be one-to-one, so this can be several pieces */
+    pub fn preimage_range(&self, range: &Range<u64>) -> IntervalSet<u64> {
+        if range.start >= range.end {
+            return IntervalSet::new()
+        }
+        self.segments.iter().enumerate().filter_map(
+            |(i, segment)| {
+                let (start, end) = (
+                    max(segment.start as i128, range.start as i128 - segment.offset),
+                    min(self.get_segment_end(i), range.end as i128 - segment.offset)
+                );
+                if start < end {
+                    Some((start as u64)..(end as u64))
+                } else {
+                    None
+                }
+            }
+        ).collect()
+    }
+
+    /* each segment shifts by a constant, so it contributes at most one input */
+    pub fn preimage(&self, value: u64) -> Vec<u64> {
+        self.segments.iter().enumerate().filter_map(
+            |(i, segment)| {
+                let input = value as i128 - segment.offset;
+                if segment.start as i128 <= input && input < self.get_segment_end(i) {
+                    Some(input as u64)
                 } else {
-                    /* .........<src_range>...
-                     * <self.src_range>.......
-                     */
-                    Some((
-                        src_range.start..self.src_range.end,
-                        (src_range.start - self.src_range.start + self.dest_range.start)..self.dest_range.end
-                    ))
+                    None
                 }
             }
+        ).collect()
+    }
+
+    /* the map that applies self and then next */
+    pub fn then(&self, next: &Self) -> Self {
+        Self::from_pieces(
+            self.segments.iter().enumerate().flat_map(
+                |(i, segment)| {
+                    let (image_start, image_end) = (segment.start as i128 + segment.offset, self.get_segment_end(i) + segment.offset);
+                    (next.find_segment(max(image_start, 0))..next.segments.len()).take_while(
+                        move |&j| (next.segments[j].start as i128) < image_end
+                    ).map(
+                        move |j| Segment {
+                            start: (max(next.segments[j].start as i128, image_start) - segment.offset) as u64,
+                            offset: segment.offset + next.segments[j].offset
+                        }
+                    )
+                }
+            )
+        )
+    }
+}
+
+impl fmt::Display for PiecewiseLinearMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            let (start, end) = (segment.start as i128, self.get_segment_end(i));
+            writeln!(f, "{}..{} -> {}..{} ({:+})", start, end, start + segment.offset, end + segment.offset, segment.offset)?;
         }
+        Ok(())
     }
 }
 
 
-struct Almanac {
+/* The categories form a graph with an edge for every 'x-to-y map' block. Maps can lead anywhere,
+ * including back to an earlier category, so paths are searched rather than followed.
+ */
+pub struct Almanac {
     seeds: Vec<u64>,
-    almanac_maps: HashMap<String, Vec<AlmanacMap>>
+    almanac_maps: HashMap<String, BTreeMap<String, Vec<AlmanacMap>>>
 }
 
 impl Almanac {
-    fn map_cat_to_cat(&self, src_cat: &String, src_num: u64) -> u64 {
-        if let Some(cat_maps) = self.almanac_maps.get(src_cat) {
-            self.map_cat_to_cat(
-                &cat_maps.first().unwrap().dest_cat,
-                cat_maps.iter()
-                    .find_map(|cat_map| cat_map.map_input(src_num))
-                    .unwrap_or(src_num)
-            )
-        } else {
-            src_num
-        }
+    pub fn seeds(&self) -> &[u64] {
+        &self.seeds
     }
 
-    fn map_cat_to_cat_range(&self, src_cat: &String, src_range: &Range<u64>) -> Vec<Range<u64>> {
-        if src_range.start == src_range.end {
-            vec![]
+    /* shortest chain of categories from one to another, including both ends */
+    pub fn find_path(&self, src_cat: &str, dest_cat: &str) -> Option<Vec<String>> {
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([src_cat]);
+        while let Some(cat) = queue.pop_front() {
+            if cat == dest_cat {
+                let mut path = vec![String::from(cat)];
+                let mut current = cat;
+                while let Some(&prev_cat) = previous.get(current) {
+                    path.push(String::from(prev_cat));
+                    current = prev_cat;
+                }
+                path.reverse();
+                return Some(path)
+            }
+            for next_cat in self.almanac_maps.get(cat).into_iter().flat_map(|cat_maps| cat_maps.keys()) {
+                if next_cat != src_cat && !previous.contains_key(next_cat.as_str()) {
+                    previous.insert(next_cat, cat);
+                    queue.push_back(next_cat);
+                }
+            }
         }
-        else if let Some(cat_maps) = self.almanac_maps.get(src_cat) {
-            let (remaining_src_ranges, dest_ranges) = cat_maps.iter()
-                .fold(
-                    (vec![src_range.clone()], vec![]), 
-                    |(src_ranges, dest_ranges), cat_map| if let Some((matched_src_range, dest_range)) = cat_map.map_input_range(src_range) {
-                        (
-                            src_ranges.into_iter().flat_map(
-                                |src_range| if src_range.start <= matched_src_range.start {
-                                    if src_range.end <= matched_src_range.start {
-                                        vec![Some(src_range)]
-                                    } else {
-                                        vec![
-                                            if src_range.start < matched_src_range.start {
-                                                Some(src_range.start..matched_src_range.start)
-                                            } else {
-                                                None
-                                            },
-                                            if src_range.end > matched_src_range.end {
-                                                Some(matched_src_range.end..src_range.end)
-                                            } else {
-                                                None
-                                            }
-                                        ]
-                                    }
-                                } else {
-                                    if src_range.start >= matched_src_range.end {
-                                        vec![Some(src_range)]
-                                    } else {
-                                        if src_range.end <= matched_src_range.end {
-                                            vec![None]
-                                        } else {
-                                            vec![Some(matched_src_range.end..src_range.end)]
-                                        }
-                                    }
-                                }.into_iter().filter_map(|item| item)
-                            ).collect(),
-                            {
-                                let mut dest_ranges = [dest_ranges, vec![dest_range]].concat();
-                                dest_ranges.sort_by(|range, other_range| range.start.partial_cmp(&other_range.start).unwrap());
-                                dest_ranges.iter().fold(
-                                    vec![], 
-                                    |dest_ranges, dest_range| if let Some(other_range) = dest_ranges.last() {
-                                        Vec::from([
-                                            Vec::from(dest_ranges.get(0..(dest_ranges.len() - 1)).unwrap()),
-                                            if dest_range.start <= other_range.end && dest_range.end >= other_range.start {
-                                                vec![min(dest_range.start, other_range.start)..max(dest_range.end, other_range.end)]
-                                            } else {
-                                                vec![other_range.clone(), dest_range.clone()]
-                                            }
-                                        ].concat())
-                                    } else {
-                                        vec![dest_range.clone()]
-                                    }
-                                )
-                            }
-                        )
-                    } else {
-                        (src_ranges, dest_ranges)
+        None
+    }
+
+    /* a chain of categories that leads back to where it started, if there is one */
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut finished: HashSet<&str> = HashSet::new();
+        let mut cats = self.almanac_maps.keys().collect::<Vec<&String>>();
+        cats.sort();
+        for start_cat in cats {
+            if finished.contains(start_cat.as_str()) {
+                continue
+            }
+            /* depth-first, keeping the current chain and the next destination to try from each category on it */
+            let mut chain: Vec<(&str, usize)> = vec![(start_cat, 0)];
+            while let Some(&mut (cat, ref mut next)) = chain.last_mut() {
+                let next_cat = self.almanac_maps.get(cat).and_then(|cat_maps| cat_maps.keys().nth(*next));
+                *next += 1;
+                if let Some(next_cat) = next_cat {
+                    if let Some(i) = chain.iter().position(|&(chain_cat, _)| chain_cat == next_cat) {
+                        return Some(chain[i..].iter().map(|&(chain_cat, _)| String::from(chain_cat)).chain([next_cat.clone()]).collect())
                     }
-                );
-            let dest_cat = &cat_maps.first().unwrap().dest_cat;
-            [
-                remaining_src_ranges.iter().map(
-                    |src_range| self.map_cat_to_cat_range(&dest_cat, src_range).into_iter()
-                ).flatten().collect::<Vec<Range<u64>>>(),
-                dest_ranges.iter().map(
-                    |dest_range| self.map_cat_to_cat_range(&dest_cat, dest_range).into_iter()
-                ).flatten().collect::<Vec<Range<u64>>>()
-            ].concat()
+                    if !finished.contains(next_cat.as_str()) {
+                        chain.push((next_cat, 0));
+                    }
+                } else {
+                    finished.insert(cat);
+                    chain.pop();
+                }
+            }
+        }
+        None
+    }
+
+    pub fn get_map(&self, src_cat: &str, dest_cat: &str) -> Option<PiecewiseLinearMap> {
+        self.find_path(src_cat, dest_cat).map(
+            |path| path.windows(2).fold(
+                PiecewiseLinearMap::identity(),
+                |composed_map, step| composed_map.then(&PiecewiseLinearMap::from_almanac_maps(&self.almanac_maps[&step[0]][&step[1]]))
+            )
+        )
+    }
+
+    pub fn map_value(&self, src_cat: &str, dest_cat: &str, value: u64) -> u64 {
+        self.get_map_or_panic(src_cat, dest_cat).apply(value)
+    }
+
+    pub fn map_range(&self, src_cat: &str, dest_cat: &str, range: &Range<u64>) -> IntervalSet<u64> {
+        self.get_map_or_panic(src_cat, dest_cat).apply_range(range)
+    }
+
+    /* the values of src_cat that map to value in dest_cat */
+    pub fn find_sources(&self, src_cat: &str, dest_cat: &str, value: u64) -> Vec<u64> {
+        self.get_map_or_panic(src_cat, dest_cat).preimage(value)
+    }
+
+    pub fn find_source_ranges(&self, src_cat: &str, dest_cat: &str, range: &Range<u64>) -> IntervalSet<u64> {
+        self.get_map_or_panic(src_cat, dest_cat).preimage_range(range)
+    }
+
+    fn get_map_or_panic(&self, src_cat: &str, dest_cat: &str) -> PiecewiseLinearMap {
+        if let Some(map) = self.get_map(src_cat, dest_cat) {
+            map
         } else {
-            vec![src_range.clone()]
+            panic!("Could not find a chain of maps from {} to {}", src_cat, dest_cat)
         }
     }
 
-    fn map_seeds_to_outputs(&self) -> Vec<u64> {
-        let seed_cat = String::from_str("seed").unwrap();
-        self.seeds.iter().map(
-            |&seed| self.map_cat_to_cat(&seed_cat, seed)
-        ).collect()
+    fn map_seeds_to_outputs(&self, seed_map: &PiecewiseLinearMap) -> Vec<u64> {
+        self.seeds.iter().map(|&seed| seed_map.apply(seed)).collect()
     }
 
-    fn map_seed_pairs_to_outputs(&self) -> Vec<Range<u64>> {
-        let seed_cat = String::from_str("seed").unwrap();
-        (0..(self.seeds.len() / 2)).flat_map(
-            |i| self.map_cat_to_cat_range(
-                &seed_cat,
-                {
-                    let (&src_start, &range) = (self.seeds.get(i * 2).unwrap(), self.seeds.get(i * 2 + 1).unwrap());
-                    &(src_start..(src_start + range))
-                }
-            ).into_iter()
+    fn get_seed_ranges(&self) -> IntervalSet<u64> {
+        self.seeds.chunks_exact(2).map(
+            |pair| if let Some(end) = pair[0].checked_add(pair[1]) {
+                pair[0]..end
+            } else {
+                panic!("Seed range starting at {} runs past the end of u64", pair[0])
+            }
         ).collect()
     }
+
+    fn map_seed_pairs_to_outputs(&self, seed_map: &PiecewiseLinearMap) -> IntervalSet<u64> {
+        self.get_seed_ranges().ranges().iter().fold(
+            IntervalSet::new(),
+            |outputs, seed_range| outputs.union(&seed_map.apply_range(seed_range))
+        )
+    }
+}
+
+
+pub fn get_seed_to_location_map(almanac_text: String) -> PiecewiseLinearMap {
+    parse_almanac(almanac_text).get_map_or_panic("seed", "location")
 }
 
 
 pub fn get_locations_for_seeds(almanac_text: String) -> u64 {
-    parse_almanac(almanac_text)
-        .map_seeds_to_outputs()
+    let almanac = parse_almanac(almanac_text);
+    let seed_map = almanac.get_map_or_panic("seed", "location");
+    almanac.map_seeds_to_outputs(&seed_map)
         .into_iter()
-        .reduce(|min_loc, loc| min(min_loc, loc)).unwrap_or(0)
+        .reduce(min).unwrap_or(0)
 }
 
 
 pub fn get_locations_for_seed_ranges(almanac_text: String) -> u64 {
-    parse_almanac(almanac_text)
-        .map_seed_pairs_to_outputs()
-        .into_iter()
-        .map(|range| range.start)
-        .reduce(|min_loc, loc| min(min_loc, loc))
+    let almanac = parse_almanac(almanac_text);
+    let seed_map = almanac.get_map_or_panic("seed", "location");
+    almanac.map_seed_pairs_to_outputs(&seed_map)
+        .min()
         .unwrap_or(0)
 }
 
 
-fn parse_almanac(almanac_text: String) -> Almanac {
+/* (seed, location) for the listed seed with the lowest location, preferring the lowest seed on a tie */
+pub fn find_seed_for_lowest_location(almanac_text: String) -> Option<(u64, u64)> {
+    let almanac = parse_almanac(almanac_text);
+    let seed_map = almanac.get_map_or_panic("seed", "location");
+    almanac.seeds.iter().map(|&seed| (seed, seed_map.apply(seed))).min_by_key(|&(seed, location)| (location, seed))
+}
+
+
+/* (seed, location) for the lowest location reachable from the seed ranges, working back from that location */
+pub fn find_seed_for_lowest_location_in_ranges(almanac_text: String) -> Option<(u64, u64)> {
+    let almanac = parse_almanac(almanac_text);
+    let seed_map = almanac.get_map_or_panic("seed", "location");
+    let seed_ranges = almanac.get_seed_ranges();
+    let location = almanac.map_seed_pairs_to_outputs(&seed_map).min()?;
+    seed_map.preimage(location).into_iter().filter(|seed| seed_ranges.contains(seed)).min().map(|seed| (seed, location))
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AlmanacIssue {
+    MalformedHeader { line_number: usize, line: String },
+    MalformedLine { line_number: usize, line: String },
+    Overflow { line_number: usize },
+    DuplicatedRange { line_number: usize, duplicate_of: usize },
+    OverlappingRanges { line_number: usize, overlaps: usize },
+    UnknownDestination { line_number: usize, category: String },
+    OddSeedCount { count: usize }
+}
+
+impl fmt::Display for AlmanacIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlmanacIssue::MalformedHeader { line_number, line } => write!(f, "Line {}: '{}' is not of the form 'x-to-y map:'", line_number, line),
+            AlmanacIssue::MalformedLine { line_number, line } => write!(f, "Line {}: '{}' is not three numbers under a map header", line_number, line),
+            AlmanacIssue::Overflow { line_number } => write!(f, "Line {}: range runs past the end of u64", line_number),
+            AlmanacIssue::DuplicatedRange { line_number, duplicate_of } => write!(f, "Line {}: duplicates line {}", line_number, duplicate_of),
+            AlmanacIssue::OverlappingRanges { line_number, overlaps } => write!(f, "Line {}: source range overlaps line {}", line_number, overlaps),
+            AlmanacIssue::UnknownDestination { line_number, category } => write!(f, "Line {}: nothing maps from destination category '{}'", line_number, category),
+            AlmanacIssue::OddSeedCount { count } => write!(f, "{} seeds cannot be read as start and length pairs", count)
+        }
+    }
+}
+
+
+/* Checks an almanac without building it. A destination is unknown if no map starts from it and it is not
+ * 'location'; an odd seed count is only a problem when the seeds are read as ranges.
+ */
+pub fn validate_almanac(almanac_text: &str, seed_ranges: bool) -> Vec<AlmanacIssue> {
+    let mut issues = Vec::new();
+    let mut current_header: Option<(&str, &str)> = None;
+    /* lines under a header that was already reported are only checked on their own */
+    let mut header_rejected = false;
+    let mut src_cats: HashSet<&str> = HashSet::new();
+    let mut dest_cats: Vec<(usize, &str)> = Vec::new();
+    let mut map_lines: BTreeMap<(&str, &str), Vec<(usize, AlmanacMap)>> = BTreeMap::new();
+    for (line_number, line) in almanac_text.split('\n').map(|line| line.trim()).enumerate().map(|(i, line)| (i + 1, line)) {
+        if line.is_empty() {
+            continue
+        }
+        if let Some(nums_term) = line.strip_prefix("seeds:") {
+            let seeds = nums_term.split_whitespace().map(|num_str| num_str.parse::<u64>()).collect::<Result<Vec<u64>, _>>();
+            match seeds {
+                Ok(seeds) => if seed_ranges && seeds.len() % 2 == 1 {
+                    issues.push(AlmanacIssue::OddSeedCount { count: seeds.len() });
+                } else if seed_ranges && seeds.chunks_exact(2).any(|pair| pair[0].checked_add(pair[1]).is_none()) {
+                    issues.push(AlmanacIssue::Overflow { line_number });
+                },
+                Err(_) => issues.push(AlmanacIssue::MalformedLine { line_number, line: String::from(line) })
+            }
+        } else if line.contains(':') {
+            current_header = parse_map_header(line);
+            header_rejected = current_header.is_none();
+            if let Some((src_cat, dest_cat)) = current_header {
+                src_cats.insert(src_cat);
+                dest_cats.push((line_number, dest_cat));
+            } else {
+                issues.push(AlmanacIssue::MalformedHeader { line_number, line: String::from(line) });
+            }
+        } else if let Some(nums) = parse_map_line(line).filter(|_| current_header.is_some() || header_rejected) {
+            match (current_header, get_almanac_map(nums)) {
+                (Some(header), Some(almanac_map)) => map_lines.entry(header).or_default().push((line_number, almanac_map)),
+                (_, None) => issues.push(AlmanacIssue::Overflow { line_number }),
+                (None, Some(_)) => ()
+            }
+        } else {
+            issues.push(AlmanacIssue::MalformedLine { line_number, line: String::from(line) });
+        }
+    }
+    for (line_number, dest_cat) in dest_cats {
+        if dest_cat != "location" && !src_cats.contains(dest_cat) {
+            issues.push(AlmanacIssue::UnknownDestination { line_number, category: String::from(dest_cat) });
+        }
+    }
+    for cat_maps in map_lines.values_mut() {
+        /* identical lines end up next to each other, in the order they appear */
+        cat_maps.sort_by_key(
+            |(line_number, almanac_map)| (almanac_map.src_range.start, almanac_map.src_range.end, almanac_map.dest_range.start, *line_number)
+        );
+        /* the earlier line reaching furthest so far is the one any later range would overlap */
+        let mut previous: Option<&(usize, AlmanacMap)> = None;
+        let mut furthest: Option<&(usize, AlmanacMap)> = None;
+        for entry in cat_maps.iter().filter(|(_, almanac_map)| !almanac_map.src_range.is_empty()) {
+            let (line_number, almanac_map) = entry;
+            if let Some((other_line_number, _)) = previous.filter(
+                |(_, other_map)| other_map.src_range == almanac_map.src_range && other_map.dest_range == almanac_map.dest_range
+            ) {
+                issues.push(AlmanacIssue::DuplicatedRange { line_number: *line_number, duplicate_of: *other_line_number });
+            } else if let Some((other_line_number, _)) = furthest.filter(|(_, other_map)| other_map.src_range.end > almanac_map.src_range.start) {
+                issues.push(AlmanacIssue::OverlappingRanges { line_number: *max(line_number, other_line_number), overlaps: *min(line_number, other_line_number) });
+            }
+            previous = Some(entry);
+            if furthest.is_none_or(|(_, other_map)| almanac_map.src_range.end > other_map.src_range.end) {
+                furthest = Some(entry);
+            }
+        }
+    }
+    issues
+}
+
+
+/* 'x-to-y map:' gives (x, y) */
+fn parse_map_header(line: &str) -> Option<(&str, &str)> {
+    let cats_term = line.strip_suffix("map:").filter(|cats_term| cats_term.ends_with(char::is_whitespace))?;
+    let (src_cat, dest_cat) = cats_term.trim_end().split_once("-to-")?;
+    let is_category = |cat: &str| !cat.is_empty() && !cat.contains(|c: char| c == '-' || c.is_whitespace());
+    if is_category(src_cat) && is_category(dest_cat) {
+        Some((src_cat, dest_cat))
+    } else {
+        None
+    }
+}
+
+
+fn parse_map_line(line: &str) -> Option<[u64; 3]> {
+    let nums = line.split_whitespace().map(|num_str| num_str.parse::<u64>().ok()).collect::<Option<Vec<u64>>>()?;
+    nums.try_into().ok()
+}
+
+
+fn get_almanac_map([dest_start, src_start, range]: [u64; 3]) -> Option<AlmanacMap> {
+    Some(AlmanacMap {
+        src_range: src_start..src_start.checked_add(range)?,
+        dest_range: dest_start..dest_start.checked_add(range)?
+    })
+}
+
+
+pub fn parse_almanac(almanac_text: String) -> Almanac {
     let mut almanac = Almanac {
         seeds: Vec::new(),
         almanac_maps: HashMap::new()
     };
     let (mut current_src_cat, mut current_dest_cat) = (String::new(), String::new());
-    for line in almanac_text.split('\n').map(|line| line.trim()) {
+    for line in almanac_text.split('\n').map(|line| line.trim()).filter(|line| !line.is_empty()) {
         if line.contains(':') {
             let parts: Vec<&str> = line.split_terminator(':').map(|term| term.trim()).collect();
             if let Some(["seeds", nums_term]) = parts.get(0..2) {
@@ -231,35 +488,42 @@ fn parse_almanac(almanac_text: String) -> Almanac {
                     )
                 );
             }
-            else {
-                let parts = parts.first().unwrap().split(['-', ' ']).collect::<Vec<&str>>();
-                (current_src_cat, current_dest_cat) = (
-                    String::from_str(parts.get(0).unwrap()).unwrap(),
-                    String::from_str(parts.get(2).unwrap()).unwrap()
-                );
+            else if let Some((src_cat, dest_cat)) = parse_map_header(line) {
+                (current_src_cat, current_dest_cat) = (String::from(src_cat), String::from(dest_cat));
+                /* a block without any ranges is still an edge, mapping every value to itself */
+                almanac.almanac_maps.entry(current_src_cat.clone()).or_default().entry(current_dest_cat.clone()).or_default();
+            } else {
+                panic!("Could not parse map header '{}'", line)
             }
         }
-        else {
-            let nums: Vec<u64> = line.split_whitespace().filter_map(
-                |num_str| if let Ok(num) = num_str.parse::<u64>() {
-                    Some(num)
-                } else {
-                    None
-                }
-            ).collect();
-            if let Some(&[dest_start, src_start, range]) = nums.get(0..3) {
-                let almanac_map = AlmanacMap {
-                    dest_cat: current_dest_cat.clone(),
-                    src_range: src_start..(src_start + range),
-                    dest_range: dest_start..(dest_start + range)
-                };
-                if let Some(almanac_maps) = almanac.almanac_maps.get_mut(&current_src_cat) {
-                    almanac_maps.push(almanac_map);
-                } else {
-                    almanac.almanac_maps.insert(current_src_cat.clone(), vec![almanac_map]);
-                }
+        else if let Some(nums) = parse_map_line(line) {
+            if let Some(almanac_map) = get_almanac_map(nums) {
+                almanac.almanac_maps.entry(current_src_cat.clone()).or_default()
+                    .entry(current_dest_cat.clone()).or_default()
+                    .push(almanac_map);
+            } else {
+                panic!("Map line '{}' runs past the end of u64", line)
             }
+        } else {
+            panic!("Could not parse '{}' as three numbers", line)
         }
     }
-    return almanac;
+    almanac
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_map_blocks_are_identity_maps() {
+        let almanac_text = "seeds: 5 3\n\nseed-to-soil map:\n\nsoil-to-location map:\n50 0 10";
+        assert!(validate_almanac(almanac_text, true).is_empty());
+        assert_eq!(get_locations_for_seeds(String::from(almanac_text)), 53);
+        assert_eq!(get_locations_for_seed_ranges(String::from(almanac_text)), 55);
+        let almanac = parse_almanac(String::from(almanac_text));
+        assert_eq!(almanac.find_path("seed", "location"), Some(vec![String::from("seed"), String::from("soil"), String::from("location")]));
+        assert_eq!(almanac.map_value("seed", "soil", 12), 12);
+    }
 }
\ No newline at end of file
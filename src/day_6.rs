@@ -1,37 +1,182 @@
+use std::cmp::min;
+use std::fmt;
+
 struct Race {
-    time: u64,
-    distance: u64
+    time: u128,
+    distance: u128
 }
 
 impl Race {
-    fn get_num_better_distances(&self) -> u64 {
-        for i in 0..(self.time + 1) {
-            if (self.time - i) * i > self.distance {
-                return self.time - 2 * i + 1
+    fn beats_record(&self, hold_time: u128) -> bool {
+        /* a distance too large for u128 certainly beats the record */
+        hold_time.checked_mul(self.time - hold_time).is_none_or(|distance| distance > self.distance)
+    }
+
+    fn get_num_better_distances(&self) -> u128 {
+        /* Holding for h travels h * (time - h), which beats the record strictly between the roots of
+         * h^2 - time * h + distance. The results are symmetric around time / 2, so only the first winning
+         * hold time is needed.
+         */
+        let half_time = self.time / 2;
+        if !self.beats_record(half_time) {
+            return 0
+        }
+        let first_hold_time = if let Some(discriminant) = self.time.checked_mul(self.time).and_then(
+            |time_squared| self.distance.checked_mul(4).and_then(|four_distance| time_squared.checked_sub(four_distance))
+        ) {
+            /* the exact root may differ from the integer one by a step either way */
+            let mut hold_time = min((self.time - discriminant.isqrt()) / 2, half_time);
+            while hold_time > 0 && self.beats_record(hold_time - 1) {
+                hold_time -= 1;
             }
+            while !self.beats_record(hold_time) {
+                hold_time += 1;
+            }
+            hold_time
+        } else {
+            /* time^2 or 4 * distance does not fit in a u128, so search for the first winning hold time instead */
+            let (mut low, mut high) = (0, half_time);
+            while low < high {
+                let middle = low + (high - low) / 2;
+                if self.beats_record(middle) {
+                    high = middle;
+                } else {
+                    low = middle + 1;
+                }
+            }
+            low
+        };
+        self.time - 2 * first_hold_time + 1
+    }
+}
+
+
+/* how the boat behaves: speed gained per ms held, an optional speed limit, and ms lost before the boat can be held */
+pub struct BoatModel {
+    pub acceleration: u128,
+    pub max_speed: Option<u128>,
+    pub start_delay: u128
+}
+
+impl BoatModel {
+    pub fn standard() -> Self {
+        BoatModel { acceleration: 1, max_speed: None, start_delay: 0 }
+    }
+
+    fn get_distance(&self, race: &Race, hold_time: u128) -> u128 {
+        let travel_time = race.time.saturating_sub(self.start_delay).saturating_sub(hold_time);
+        let speed = self.acceleration.saturating_mul(hold_time);
+        self.max_speed.map_or(speed, |max_speed| min(speed, max_speed)).saturating_mul(travel_time)
+    }
+
+    /* Distance is the lesser of a downward parabola and a falling line in the hold time, so it rises to a
+     * single peak and falls away after it, and the winning hold times are the ones around that peak.
+     */
+    fn analyse(&self, race: &Race) -> RaceOutcome {
+        let beats_record = |hold_time: u128| self.get_distance(race, hold_time) > race.distance;
+        let optimal_hold_time = find_first(0, race.time, |hold_time| self.get_distance(race, hold_time + 1) <= self.get_distance(race, hold_time));
+        let best_distance = self.get_distance(race, optimal_hold_time);
+        let num_winning_hold_times = if beats_record(optimal_hold_time) {
+            let first_hold_time = find_first(0, optimal_hold_time, beats_record);
+            let end_hold_time = find_first(optimal_hold_time, race.time + 1, |hold_time| hold_time > race.time || !beats_record(hold_time));
+            end_hold_time - first_hold_time
+        } else {
+            0
+        };
+        RaceOutcome { num_winning_hold_times, optimal_hold_time, best_distance, margin: best_distance.checked_sub(race.distance).filter(|&margin| margin > 0) }
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaceOutcome {
+    pub num_winning_hold_times: u128,
+    pub optimal_hold_time: u128,
+    pub best_distance: u128,
+    /* how far the best run beats the record by, if it beats it at all */
+    pub margin: Option<u128>
+}
+
+impl fmt::Display for RaceOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ways to win, holding {} ms goes {} mm", self.num_winning_hold_times, self.optimal_hold_time, self.best_distance)?;
+        if let Some(margin) = self.margin {
+            write!(f, " and beats the record by {}", margin)
+        } else {
+            write!(f, " and does not beat the record")
         }
-        return 0
     }
 }
 
 
-pub fn get_product_of_num_possible_record_breaks(races_text: String) -> u64 {
+/* the first value in low..high for which the predicate holds, given that it holds for everything after that, or high */
+fn find_first(low: u128, high: u128, predicate: impl Fn(u128) -> bool) -> u128 {
+    let (mut low, mut high) = (low, high);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if predicate(middle) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    low
+}
+
+
+pub fn get_product_of_num_possible_record_breaks(races_text: String) -> u128 {
     parse_races(races_text).iter().fold(
         1,
-        |product, race| race.get_num_better_distances() * product
+        |product, race| race.get_num_better_distances().checked_mul(product).unwrap_or_else(
+            || panic!("Product of the numbers of ways to win exceeds u128")
+        )
     )
 }
 
 
-pub fn get_single_race_num_possible_record_breaks(races_text: String) -> u64 {
+pub fn get_single_race_num_possible_record_breaks(races_text: String) -> u128 {
     parse_races_bad_kerning(races_text).get_num_better_distances()
 }
 
 
+pub fn analyse_races(races_text: String, model: &BoatModel) -> Vec<RaceOutcome> {
+    parse_races(races_text).iter().map(|race| model.analyse(race)).collect()
+}
+
+
+pub fn analyse_single_race(races_text: String, model: &BoatModel) -> RaceOutcome {
+    model.analyse(&parse_races_bad_kerning(races_text))
+}
+
+
+/* parses models of the form 'acceleration=2,max-speed=10,start-delay=1', where missing terms keep their standard values */
+pub fn parse_boat_model(model_text: &str) -> BoatModel {
+    let mut model = BoatModel::standard();
+    for term in model_text.split(',').map(|term| term.trim()).filter(|term| !term.is_empty()) {
+        if let Some((name, value_str)) = term.split_once('=') {
+            let value = if let Ok(value) = value_str.trim().parse::<u128>() {
+                value
+            } else {
+                panic!("Could not interpret '{}' as a number for model term '{}'", value_str, term)
+            };
+            match name.trim() {
+                "acceleration" => model.acceleration = value,
+                "max-speed" => model.max_speed = Some(value),
+                "start-delay" => model.start_delay = value,
+                _ => panic!("Unknown model term '{}', expected 'acceleration', 'max-speed' or 'start-delay'", name.trim())
+            }
+        } else {
+            panic!("Could not parse name and value for model term '{}'", term)
+        }
+    }
+    model
+}
+
+
 fn parse_races(races_text: String) -> Vec<Race> {
     if let Some([time_line, distance_line]) = races_text.split('\n').map(|line| line.trim()).collect::<Vec<&str>>().get(0..2) {
         time_line.split_whitespace().zip(distance_line.split_whitespace()).filter_map(
-            |(time_str, distance_str)| if let (Ok(time), Ok(distance)) = (time_str.parse::<u64>(), distance_str.parse::<u64>()) {
+            |(time_str, distance_str)| if let (Ok(time), Ok(distance)) = (time_str.parse::<u128>(), distance_str.parse::<u128>()) {
                 Some(Race { time, distance })
             } else {
                 None
@@ -48,21 +193,60 @@ fn parse_races_bad_kerning(races_text: String) -> Race {
         let [time, distance] = [time_line, distance_line].map(
             |line| 
             line.split_whitespace()
-                .filter_map(
-                    |num_str|
-                    if let Ok(_) = num_str.parse::<u64>() {
-                        Some(num_str) 
-                    } else {
-                        None 
-                    }
-                ).collect::<Vec<&str>>()
+                .filter(|num_str| num_str.parse::<u128>().is_ok())
+                .collect::<Vec<&str>>()
                 .concat()
-                .parse::<u64>()
-                .ok()
-                .unwrap_or(0)
+                .parse::<u128>()
+                .unwrap_or_else(|_| panic!("Could not read '{}' as a single number that fits in a u128", line))
         );
         Race { time, distance }
     } else {
         panic!("Could not parse races")
     }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(time: u128, distance: u128) -> u128 {
+        Race { time, distance }.get_num_better_distances()
+    }
+
+    #[test]
+    fn matches_counting_every_hold_time() {
+        for time in 0..60 {
+            for distance in 0..(time * time / 4 + 3) {
+                assert_eq!(count(time, distance), (0..=time).filter(|hold_time| hold_time * (time - hold_time) > distance).count() as u128);
+            }
+        }
+    }
+
+    #[test]
+    fn tied_records_do_not_count() {
+        assert_eq!(count(30, 200), 9);
+        assert_eq!(count(4, 4), 0);
+        assert_eq!(count(4, 3), 1);
+    }
+
+    #[test]
+    fn zero_distance_and_zero_time() {
+        assert_eq!(count(10, 0), 9);
+        assert_eq!(count(1, 0), 0);
+        assert_eq!(count(0, 0), 0);
+        assert_eq!(count(0, 5), 0);
+    }
+
+    #[test]
+    fn times_whose_square_overflows() {
+        /* time^2 is just over 2^128, while the best distance (2^63 + 1)^2 still fits */
+        let time = (1 << 64) + 2;
+        let best_distance = ((1 << 63) + 1) * ((1 << 63) + 1);
+        assert_eq!(count(time, 0), time - 1);
+        assert_eq!(count(time, best_distance - 1), 1);
+        assert_eq!(count(time, best_distance), 0);
+        assert_eq!(count(time, (1 << 62) * (time - (1 << 62))), time - (1 << 63) - 1);
+        assert_eq!(count(u128::MAX, u128::MAX), u128::MAX - 3);
+    }
 }
\ No newline at end of file
@@ -1,218 +1,131 @@
-use std::{collections::HashMap, cmp::{Ordering, min}};
-
-
-#[derive(PartialEq, Eq, Hash, PartialOrd, Clone, Copy)]
-enum Card {
-    Ace,
-    King,
-    Queen,
-    Jack,
-    Ten,
-    Nine,
-    Eight,
-    Seven,
-    Six,
-    Five,
-    Four,
-    Three,
-    Two
+use std::collections::HashMap;
+
+
+/* everything that decides how hands rank against each other */
+pub struct RuleSet {
+    pub hand_size: usize,
+    /* strongest first */
+    pub card_order: Vec<char>,
+    /* cards that can stand in for any other when working out the hand type */
+    pub wild_cards: Vec<char>,
+    /* group sizes a hand needs for each type, strongest first, e.g. [3, 2] for a full house */
+    pub hand_types: Vec<Vec<usize>>
 }
 
-
-impl Card {
-    fn from_char(c: char) -> Self {
-        match c {
-            'A' => Self::Ace,
-            'K' => Self::King,
-            'Q' => Self::Queen,
-            'J' => Self::Jack,
-            'T' => Self::Ten,
-            '9' => Self::Nine,
-            '8' => Self::Eight,
-            '7' => Self::Seven,
-            '6' => Self::Six,
-            '5' => Self::Five,
-            '4' => Self::Four,
-            '3' => Self::Three,
-            '2' => Self::Two,
-            _ => panic!("Tried to map invalid symbol to card")
+impl RuleSet {
+    pub fn standard() -> Self {
+        RuleSet {
+            hand_size: 5,
+            card_order: "AKQJT98765432".chars().collect(),
+            wild_cards: Vec::new(),
+            hand_types: Self::get_poker_hand_types()
         }
     }
 
-    fn repr(&self) -> char {
-        match &self {
-            Card::Ace => 'A',
-            Card::King => 'K',
-            Card::Queen => 'Q',
-            Card::Jack => 'J',
-            Card::Ten => 'T',
-            Card::Nine => '9',
-            Card::Eight => '8',
-            Card::Seven => '7',
-            Card::Six => '6',
-            Card::Five => '5',
-            Card::Four => '4',
-            Card::Three => '3',
-            Card::Two => '2',
+    pub fn jokers() -> Self {
+        RuleSet {
+            hand_size: 5,
+            card_order: "AKQT98765432J".chars().collect(),
+            wild_cards: vec!['J'],
+            hand_types: Self::get_poker_hand_types()
         }
     }
 
-    fn get_ordered_list() -> [Self; 13] {
-        [
-            Self::Ace,
-            Self::King,
-            Self::Queen,
-            Self::Jack,
-            Self::Ten,
-            Self::Nine,
-            Self::Eight,
-            Self::Seven,
-            Self::Six,
-            Self::Five,
-            Self::Four,
-            Self::Three,
-            Self::Two,
+    fn get_poker_hand_types() -> Vec<Vec<usize>> {
+        vec![
+            vec![5],        // Five of a kind
+            vec![4],        // Four of a kind
+            vec![3, 2],     // Full house
+            vec![3],        // Three of a kind
+            vec![2, 2],     // Two pair
+            vec![2],        // One pair
+            vec![1]         // High card
         ]
     }
-}
-
-
-struct Hand {
-    cards: [Card; 5],
-    bid: u32,
-    use_jokers: bool
-}
 
+    fn get_strength(&self, card: char) -> u32 {
+        if let Some(position) = self.card_order.iter().position(|&ordered_card| ordered_card == card) {
+            (self.card_order.len() - 1 - position) as u32
+        } else {
+            panic!("Card '{}' is not in the card order", card)
+        }
+    }
 
-impl Hand {
-    fn count_cards(&self) -> [Option<(Card, usize)>; 5] {
-        let mut card_counts: HashMap<Card, usize> = HashMap::new();
-        let mut num_jokers = 0;
-        for card in self.cards {
-            if self.use_jokers && card == Card::Jack {
-                num_jokers += 1
-            } else if let Some(count) = card_counts.get_mut(&card) {
-                *count += 1;
+    /* 0 if no type fits, otherwise higher for stronger types */
+    fn get_hand_type(&self, cards: &[char]) -> u32 {
+        let mut card_counts: HashMap<char, usize> = HashMap::new();
+        let mut num_wild_cards = 0;
+        for card in cards {
+            if self.wild_cards.contains(card) {
+                num_wild_cards += 1;
             } else {
-                card_counts.insert(card, 1);
-
+                *card_counts.entry(*card).or_insert(0) += 1;
             }
         }
-        let mut card_count_list = Card::get_ordered_list().into_iter().filter_map(
-            |card| if let Some(&count) = card_counts.get(&card) {
-                Some((card, count))
-            } else {
-                None
-            }
-        ).collect::<Vec<(Card, usize)>>();
-        card_count_list.sort_by(|(_, count_a), (_, count_b)| count_b.cmp(count_a));
-        if num_jokers == 5 {
-            card_count_list = vec![(Card::Jack, 5)];
+        let mut counts = card_counts.into_values().collect::<Vec<usize>>();
+        counts.sort_by(|count_a, count_b| count_b.cmp(count_a));
+        /* pairing the largest groups with the largest required groups needs the fewest wild cards */
+        if let Some(i) = self.hand_types.iter().position(
+            |group_sizes| group_sizes.iter().enumerate().map(
+                |(j, &group_size)| group_size.saturating_sub(counts.get(j).copied().unwrap_or(0))
+            ).sum::<usize>() <= num_wild_cards
+        ) {
+            (self.hand_types.len() - i) as u32
         } else {
-            for (_, count) in card_count_list.iter_mut() {
-                let jokers_to_add = min(num_jokers, 5 - *count);
-                *count += jokers_to_add;
-                num_jokers -= jokers_to_add;
-            }
+            0
         }
-        [
-            if let Some(pair) = card_count_list.get(0) { Some(*pair) } else { None },
-            if let Some(pair) = card_count_list.get(1) { Some(*pair) } else { None },
-            if let Some(pair) = card_count_list.get(2) { Some(*pair) } else { None },
-            if let Some(pair) = card_count_list.get(3) { Some(*pair) } else { None },
-            if let Some(pair) = card_count_list.get(4) { Some(*pair) } else { None },
-        ]
     }
 
-    fn get_hand_type(&self) -> usize {
-        match self.count_cards() {
-            [Some((_, 5)), ..] => 6,                    // Five of a kind
-            [Some((_, 4)), ..] => 5,                    // Four of a kind
-            [Some((_, 3)), Some((_, 2)), ..] => 4,      // Full house
-            [Some((_, 3)), ..] => 3,                    // Three of a kind
-            [Some((_, 2)), Some((_, 2)), ..] => 2,      // Two pair
-            [Some((_, 2)), ..] => 1,                    // One pair
-            _ => 0                                      // High card
-        }
-    }
-}
-
-
-impl PartialEq for Hand {
-    fn eq(&self, other: &Self) -> bool {
-        self.cards == other.cards
-    }
-}
-
-
-impl Eq for Hand {
-
-}
-
-
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let (self_hand_type, other_hand_type) = (self.get_hand_type(), other.get_hand_type());
-        if self_hand_type > other_hand_type {
-            Some(Ordering::Greater)
-        } else if self_hand_type < other_hand_type {
-            Some(Ordering::Less)
-        } else {
-            Some(
-                self.cards.iter().zip(other.cards.iter()).find_map(
-                    |(card_a, card_b)| if card_a != card_b {
-                        if !self.use_jokers || (*card_a != Card::Jack && *card_b != Card::Jack) {
-                            card_b.partial_cmp(&card_a)
-                        } else if *card_b == Card::Jack {
-                            Some(Ordering::Greater)
-                        } else {
-                            Some(Ordering::Less)
-                        }
-                    } else {
-                        None
-                    }
-                ).unwrap_or(Ordering::Equal)
-            )
+    /* the hand type followed by the strength of each card in order, so hands compare as plain numbers */
+    fn get_sort_key(&self, cards: &[char]) -> u128 {
+        let bits_for = |num_values: usize| (num_values.max(2) - 1).ilog2() + 1;
+        let (type_bits, card_bits) = (bits_for(self.hand_types.len() + 1), bits_for(self.card_order.len()));
+        if type_bits as usize + card_bits as usize * self.hand_size > 128 {
+            panic!("Hands of {} cards from {} ranks are too large for a sort key", self.hand_size, self.card_order.len())
         }
+        cards.iter().fold(
+            self.get_hand_type(cards) as u128,
+            |key, &card| key << card_bits | self.get_strength(card) as u128
+        )
     }
 }
 
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
-    }
+struct Hand {
+    bid: u32,
+    sort_key: u128
 }
 
 
-pub fn calculate_total_winnings(hands_text: String, use_jokers: bool) -> u32 {
-    let mut hands = parse_hands(hands_text, use_jokers);
-    hands.sort();
+pub fn calculate_total_winnings(hands_text: String, rules: &RuleSet) -> u64 {
+    let mut hands = parse_hands(hands_text, rules);
+    hands.sort_by_key(|hand| hand.sort_key);
     hands.iter()
         .enumerate()
         .fold(
             0,
-            |total, (i, hand)| total + (i + 1) as u32 * hand.bid
+            |total, (i, hand)| ((i + 1) as u64).checked_mul(hand.bid as u64).and_then(|winnings| total.checked_add(winnings)).unwrap_or_else(
+                || panic!("Total winnings exceed u64 at rank {}", i + 1)
+            )
         )
 }
 
 
-fn parse_hands(hands_text: String, use_jokers: bool) -> Vec<Hand> {
+fn parse_hands(hands_text: String, rules: &RuleSet) -> Vec<Hand> {
     hands_text.split('\n').map(
-        |line_text| if let Some([cards_text, bid_text]) = line_text.trim().split_whitespace().collect::<Vec<&str>>().get(0..2) {
-            let cards = if let Some(&[c1, c2, c3, c4, c5]) = cards_text.chars().collect::<Vec<char>>().get(0..5) {
-                [c1, c2, c3, c4, c5].map(|c| Card::from_char(c))
-            } else {
-                panic!("Could not parse cards from '{}'", line_text)
-            };
+        |line_text| if let Some([cards_text, bid_text]) = line_text.split_whitespace().collect::<Vec<&str>>().get(0..2) {
+            let cards = cards_text.chars().collect::<Vec<char>>();
+            if cards.len() != rules.hand_size {
+                panic!("Expected {} cards in '{}'", rules.hand_size, line_text)
+            }
             let bid = if let Ok(bid) = bid_text.parse::<u32>() {
                 bid
             } else {
                 panic!("Could not parse bid from '{}'", bid_text)
             };
-            Hand { cards, bid, use_jokers }
+            Hand { bid, sort_key: rules.get_sort_key(&cards) }
         } else {
             panic!("Could not parse cards_text and bid_text from '{}'", line_text)
         }
     ).collect()
-}
+}
\ No newline at end of file
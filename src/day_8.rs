@@ -33,7 +33,7 @@ impl Map {
             }
             current_node = self.nodes.get(next_node_name).unwrap();
         }
-        return move_count;
+        move_count
     }
 
     fn get_cycle_info(&self, start: &String) -> (usize, usize, usize) {
@@ -63,16 +63,12 @@ impl Map {
             current_node = self.nodes.get(node_name).unwrap();
             move_count += 1;
         }
-        return (cycle_start, cycle_length, z_pos)
+        (cycle_start, cycle_length, z_pos)
     }
 
     fn calculate_moves_from_any_a(&self) -> usize {
-        let mut cycle_infos = self.nodes.iter().filter_map(
-            |(name, _)| if name.ends_with('A') {
-                Some(name)
-            } else {
-                None
-            }
+        let mut cycle_infos = self.nodes.keys().filter(
+            |name| name.ends_with('A')
         ).map(|name| self.get_cycle_info(name)).collect::<Vec<(usize, usize, usize)>>();
         cycle_infos.sort_by(|(_, cycle_length_a, _), (_, cycle_length_b, _)| cycle_length_b.cmp(cycle_length_a));
         let (_, _, final_z_pos) = cycle_infos.into_iter().reduce(
@@ -167,7 +163,7 @@ fn parse_map(map_text: String) -> Map {
                         if let Some([left, right]) = left_right.split(',').map(|piece| piece.trim()).collect::<Vec<&str>>().get(0..2) {
                             if let (Some(left), Some(right)) = (left.strip_prefix('('), right.strip_suffix(')')) {
                                 Some((
-                                    String::from(node_title.clone()),
+                                    String::from(*node_title),
                                     Node {
                                         left: String::from(left),
                                         right: String::from(right)
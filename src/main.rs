@@ -1,37 +1,105 @@
-mod day_1;
-mod day_2;
-mod day_3;
-mod day_4;
-mod day_5;
-mod day_6;
-mod day_7;
-mod day_8;
-mod utilities;
-
-use utilities::read_input_file;
+use advent_of_code_2023::{
+    day_1::{self, DigitVocabulary, Language},
+    day_2,
+    day_3::{self, GearRule},
+    day_4,
+    day_5,
+    day_6,
+    day_7,
+    day_8,
+    day_24,
+    day_25,
+    utilities::read_input_file
+};
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)).map(|value| value.as_str())
+}
+
 
 fn main() {
-    println!("Day 1a: {}", day_1::sum_first_and_last_digits(read_input_file("day_1.txt"), false));
-    println!("Day 1b: {}", day_1::sum_first_and_last_digits(read_input_file("day_1.txt"), true));
+    let args = std::env::args().collect::<Vec<String>>();
+
+    for (part, vocabulary) in [("a", DigitVocabulary::numerals()), ("b", DigitVocabulary::spelled(Language::English, false))] {
+        match day_1::sum_first_and_last_digits(read_input_file("day_1.txt"), &vocabulary) {
+            Ok(sum) => println!("Day 1{}: {}", part, sum),
+            Err(error) => println!("Day 1{}: {}", part, error)
+        }
+        match get_option(&args, "--day-1-trace") {
+            Some("table") => println!("{}", day_1::trace_first_and_last_digits(read_input_file("day_1.txt"), &vocabulary).to_table()),
+            Some("csv") => println!("{}", day_1::trace_first_and_last_digits(read_input_file("day_1.txt"), &vocabulary).to_csv()),
+            Some(format) => panic!("Unknown trace format '{}', expected 'table' or 'csv'", format),
+            None => ()
+        }
+    }
     
-    println!("Day 2a: {}", day_2::sum_valid_game_ids(read_input_file("day_2.txt"), (12, 13, 14)));
+    let bag = day_2::parse_bag(get_option(&args, "--bag").unwrap_or("red=12,green=13,blue=14"));
+    println!("Day 2a: {}", day_2::sum_valid_game_ids(read_input_file("day_2.txt"), &bag));
     println!("Day 2b: {}", day_2::sum_powers_of_minimum_grabs(read_input_file("day_2.txt")));
 
     println!("Day 3a: {}", day_3::sum_part_numbers(read_input_file("day_3.txt")));
-    println!("Day 3b: {}", day_3::sum_gear_ratios(read_input_file("day_3.txt")));
+    println!("Day 3b: {}", day_3::sum_gear_ratios(read_input_file("day_3.txt"), &GearRule::standard()));
+    if args.iter().any(|arg| arg == "--day-3-report") {
+        print!("{}", day_3::parse_schematic(read_input_file("day_3.txt")));
+    }
 
-    println!("Day 4a: {}", day_4::sum_cards(read_input_file("day_4.txt")));
+    let scoring_rule = match get_option(&args, "--day-4-scoring") {
+        Some("doubling") | None => day_4::ScoringRule::Doubling,
+        Some("linear") => day_4::ScoringRule::Linear,
+        Some("fibonacci") => day_4::ScoringRule::Fibonacci,
+        Some(rule) => panic!("Unknown scoring rule '{}', expected 'doubling', 'linear' or 'fibonacci'", rule)
+    };
+    println!("Day 4a: {}", day_4::sum_cards(read_input_file("day_4.txt"), &scoring_rule));
     println!("Day 4b: {}", day_4::count_cards(read_input_file("day_4.txt")));
+    if args.iter().any(|arg| arg == "--day-4-ledger") {
+        for card in day_4::get_copy_ledger(read_input_file("day_4.txt")) {
+            println!(
+                "  Card {}: {} copies, won from {}",
+                card.card_id,
+                card.copies,
+                if card.won_from.is_empty() {
+                    String::from("none")
+                } else {
+                    card.won_from.iter().map(|(card_id, copies)| format!("card {} x{}", card_id, copies)).collect::<Vec<String>>().join(", ")
+                }
+            );
+        }
+    }
 
+    for issue in day_5::validate_almanac(&read_input_file("day_5.txt"), true) {
+        eprintln!("Day 5 almanac: {}", issue);
+    }
     println!("Day 5a: {}", day_5::get_locations_for_seeds(read_input_file("day_5.txt")));
     println!("Day 5b: {}", day_5::get_locations_for_seed_ranges(read_input_file("day_5.txt")));
+    if args.iter().any(|arg| arg == "--day-5-audit") {
+        for (part, seed_and_location) in [
+            ("a", day_5::find_seed_for_lowest_location(read_input_file("day_5.txt"))),
+            ("b", day_5::find_seed_for_lowest_location_in_ranges(read_input_file("day_5.txt")))
+        ] {
+            if let Some((seed, location)) = seed_and_location {
+                println!("  Part {}: seed {} gives location {}", part, seed, location);
+            }
+        }
+    }
 
     println!("Day 6a: {}", day_6::get_product_of_num_possible_record_breaks(read_input_file("day_6.txt")));
     println!("Day 6b: {}", day_6::get_single_race_num_possible_record_breaks(read_input_file("day_6.txt")));
+    if let Some(model_text) = get_option(&args, "--day-6-model") {
+        let model = day_6::parse_boat_model(model_text);
+        for (i, outcome) in day_6::analyse_races(read_input_file("day_6.txt"), &model).iter().enumerate() {
+            println!("  Race {}: {}", i + 1, outcome);
+        }
+        println!("  Single race: {}", day_6::analyse_single_race(read_input_file("day_6.txt"), &model));
+    }
 
-    println!("Day 7a: {}", day_7::calculate_total_winnings(read_input_file("day_7.txt"), false));
-    println!("Day 7b: {}", day_7::calculate_total_winnings(read_input_file("day_7.txt"), true));
+    println!("Day 7a: {}", day_7::calculate_total_winnings(read_input_file("day_7.txt"), &day_7::RuleSet::standard()));
+    println!("Day 7b: {}", day_7::calculate_total_winnings(read_input_file("day_7.txt"), &day_7::RuleSet::jokers()));
 
     println!("Day 8a: {}", day_8::count_moves_for_map(read_input_file("day_8.txt")));
     println!("Day 8b: {}", day_8::count_moves_from_any_a_for_map(read_input_file("day_8.txt")));
+
+    println!("Day 24a: {}", day_24::count_intersections_in_test_area(read_input_file("day_24.txt"), 200000000000000, 400000000000000));
+    println!("Day 24b: {}", day_24::sum_rock_start_coordinates(read_input_file("day_24.txt")));
+
+    println!("Day 25: {}", day_25::multiply_group_sizes_after_cut(read_input_file("day_25.txt")));
 }
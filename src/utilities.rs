@@ -1,8 +1,601 @@
 use std::fs;
+use std::collections::{BinaryHeap, HashMap};
+use std::cmp::{min, max};
+use std::ops::{Index, IndexMut, Range};
 
 pub fn read_input_file(file_name: &str) -> String {
     let path = String::from("inputs/") + file_name;
-    fs::read_to_string(&path).expect(
-        format!("Could not read {}", path).as_str()
-    )
+    fs::read_to_string(&path).unwrap_or_else(|_| panic!("Could not read {}", path))
+}
+
+pub struct UndirectedGraph {
+    node_indices: HashMap<String, usize>,
+    adjacency: Vec<HashMap<usize, u64>>
+}
+
+impl Default for UndirectedGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UndirectedGraph {
+    pub fn new() -> Self {
+        UndirectedGraph { node_indices: HashMap::new(), adjacency: Vec::new() }
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    pub fn add_node(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.node_indices.get(name) {
+            index
+        } else {
+            let index = self.adjacency.len();
+            self.node_indices.insert(String::from(name), index);
+            self.adjacency.push(HashMap::new());
+            index
+        }
+    }
+
+    pub fn add_edge(&mut self, name_a: &str, name_b: &str) {
+        let (a, b) = (self.add_node(name_a), self.add_node(name_b));
+        if a != b {
+            *self.adjacency[a].entry(b).or_insert(0) += 1;
+            *self.adjacency[b].entry(a).or_insert(0) += 1;
+        }
+    }
+
+    /* Stoer-Wagner: returns the weight of a minimum cut and the nodes on one side of it */
+    pub fn find_minimum_cut(&self) -> Option<(u64, Vec<usize>)> {
+        let num_nodes = self.num_nodes();
+        let mut weights = self.adjacency.clone();
+        let mut members = (0..num_nodes).map(|node| vec![node]).collect::<Vec<Vec<usize>>>();
+        let mut active = vec![true; num_nodes];
+        let mut best_cut: Option<(u64, Vec<usize>)> = None;
+        for _ in 1..num_nodes {
+            let (s, t, cut_weight) = Self::maximum_adjacency_search(&weights, &active);
+            if best_cut.as_ref().is_none_or(|(best_weight, _)| cut_weight < *best_weight) {
+                best_cut = Some((cut_weight, members[t].clone()));
+            }
+            /* merge t into s */
+            for (u, weight) in std::mem::take(&mut weights[t]) {
+                weights[u].remove(&t);
+                if u != s {
+                    *weights[s].entry(u).or_insert(0) += weight;
+                    *weights[u].entry(s).or_insert(0) += weight;
+                }
+            }
+            let t_members = std::mem::take(&mut members[t]);
+            members[s].extend(t_members);
+            active[t] = false;
+        }
+        best_cut
+    }
+
+    fn maximum_adjacency_search(weights: &[HashMap<usize, u64>], active: &[bool]) -> (usize, usize, u64) {
+        let mut keys = vec![0; weights.len()];
+        let mut added = vec![false; weights.len()];
+        let mut queue = active.iter().enumerate().filter(
+            |(_, &is_active)| is_active
+        ).map(|(node, _)| (0, node)).collect::<BinaryHeap<(u64, usize)>>();
+        let (mut previous, mut last) = (None, None);
+        while let Some((key, node)) = queue.pop() {
+            if added[node] || key != keys[node] {
+                continue;
+            }
+            added[node] = true;
+            (previous, last) = (last, Some(node));
+            for (&neighbor, &weight) in weights[node].iter() {
+                if !added[neighbor] {
+                    keys[neighbor] += weight;
+                    queue.push((keys[neighbor], neighbor));
+                }
+            }
+        }
+        if let (Some(s), Some(t)) = (previous, last) {
+            (s, t, keys[t])
+        } else {
+            panic!("Maximum adjacency search needs at least two active nodes")
+        }
+    }
+}
+
+
+const NEIGHBOR_OFFSETS_4: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+const NEIGHBOR_OFFSETS_8: [(isize, isize); 8] = [
+    (-1, -1), (-1, 0), (-1, 1),
+    (0, -1),           (0, 1),
+    (1, -1),  (1, 0),  (1, 1)
+];
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid<T> {
+    num_rows: usize,
+    num_cols: usize,
+    cells: Vec<T>
+}
+
+impl<T> Grid<T> {
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Self {
+        let num_rows = rows.len();
+        let num_cols = rows.first().map_or(0, |row| row.len());
+        if rows.iter().any(|row| row.len() != num_cols) {
+            panic!("Tried to build a grid from rows of differing lengths")
+        }
+        Grid { num_rows, num_cols, cells: rows.into_iter().flatten().collect() }
+    }
+
+    pub fn from_text(text: &str, parse_cell: impl Fn(char) -> T) -> Self {
+        Self::from_rows(parse_text_rows(text, parse_cell))
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.num_cols
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        if row < self.num_rows && col < self.num_cols {
+            self.cells.get(row * self.num_cols + col)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        if row < self.num_rows && col < self.num_cols {
+            self.cells.get_mut(row * self.num_cols + col)
+        } else {
+            None
+        }
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let num_cols = self.num_cols;
+        (0..self.num_rows).flat_map(move |row| (0..num_cols).map(move |col| (row, col)))
+    }
+
+    pub fn neighbors_4(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.offset_positions(row, col, &NEIGHBOR_OFFSETS_4)
+    }
+
+    pub fn neighbors_8(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.offset_positions(row, col, &NEIGHBOR_OFFSETS_8)
+    }
+
+    fn offset_positions(&self, row: usize, col: usize, offsets: &'static [(isize, isize)]) -> impl Iterator<Item = (usize, usize)> {
+        let (num_rows, num_cols) = (self.num_rows, self.num_cols);
+        offsets.iter().filter_map(
+            move |&(row_offset, col_offset)| if let (Some(row), Some(col)) = (
+                row.checked_add_signed(row_offset), col.checked_add_signed(col_offset)
+            ) {
+                if row < num_rows && col < num_cols { Some((row, col)) } else { None }
+            } else {
+                None
+            }
+        )
+    }
+
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.cells[(row * self.num_cols)..((row + 1) * self.num_cols)]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        (0..self.num_rows).map(|row| self.row(row))
+    }
+
+    pub fn column(&self, col: usize) -> impl Iterator<Item = &T> {
+        self.cells.iter().skip(col).step_by(self.num_cols.max(1)).take(self.num_rows)
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.num_cols).map(|col| self.column(col))
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn filled(num_rows: usize, num_cols: usize, value: T) -> Self {
+        Grid { num_rows, num_cols, cells: vec![value; num_rows * num_cols] }
+    }
+
+    /* like from_rows, but short rows are padded with fill instead of rejected */
+    pub fn from_rows_padded(rows: Vec<Vec<T>>, fill: T) -> Self {
+        let num_cols = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        Self::from_rows(
+            rows.into_iter().map(
+                |mut row| {
+                    row.resize(num_cols, fill.clone());
+                    row
+                }
+            ).collect()
+        )
+    }
+
+    pub fn from_text_padded(text: &str, fill: T, parse_cell: impl Fn(char) -> T) -> Self {
+        Self::from_rows_padded(parse_text_rows(text, parse_cell), fill)
+    }
+
+    pub fn transposed(&self) -> Self {
+        Grid {
+            num_rows: self.num_cols,
+            num_cols: self.num_rows,
+            cells: self.columns().flat_map(|column| column.cloned()).collect()
+        }
+    }
+
+    pub fn rotated_clockwise(&self) -> Self {
+        Grid {
+            num_rows: self.num_cols,
+            num_cols: self.num_rows,
+            cells: self.columns().flat_map(|column| column.cloned().collect::<Vec<T>>().into_iter().rev()).collect()
+        }
+    }
+
+    pub fn rotated_counterclockwise(&self) -> Self {
+        Grid {
+            num_rows: self.num_cols,
+            num_cols: self.num_rows,
+            cells: (0..self.num_cols).rev().flat_map(|col| self.column(col).cloned()).collect()
+        }
+    }
+}
+
+fn parse_text_rows<T>(text: &str, parse_cell: impl Fn(char) -> T) -> Vec<Vec<T>> {
+    let lines = text.split('\n').map(|line| line.trim_end_matches('\r')).collect::<Vec<&str>>();
+    let num_lines = lines.iter().rposition(|line| !line.is_empty()).map_or(0, |i| i + 1);
+    lines[..num_lines].iter().map(|line| line.chars().map(&parse_cell).collect()).collect()
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        if let Some(cell) = self.get(row, col) {
+            cell
+        } else {
+            panic!("Position ({}, {}) is outside of a {}x{} grid", row, col, self.num_rows, self.num_cols)
+        }
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        let (num_rows, num_cols) = (self.num_rows, self.num_cols);
+        if let Some(cell) = self.get_mut(row, col) {
+            cell
+        } else {
+            panic!("Position ({}, {}) is outside of a {}x{} grid", row, col, num_rows, num_cols)
+        }
+    }
+}
+
+
+/* A set of values stored as sorted, non-empty, non-overlapping and non-touching ranges */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntervalSet<T> {
+    ranges: Vec<Range<T>>
+}
+
+impl<T: Ord + Copy> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet { ranges: Vec::new() }
+    }
+
+    pub fn from_ranges(ranges: impl IntoIterator<Item = Range<T>>) -> Self {
+        let mut ranges = ranges.into_iter().filter(|range| range.start < range.end).collect::<Vec<Range<T>>>();
+        ranges.sort_by_key(|range| range.start);
+        IntervalSet { ranges: Self::coalesce(ranges) }
+    }
+
+    fn coalesce(sorted_ranges: Vec<Range<T>>) -> Vec<Range<T>> {
+        let mut coalesced: Vec<Range<T>> = Vec::with_capacity(sorted_ranges.len());
+        for range in sorted_ranges {
+            if let Some(last) = coalesced.last_mut().filter(|last| range.start <= last.end) {
+                last.end = max(last.end, range.end);
+            } else {
+                coalesced.push(range);
+            }
+        }
+        coalesced
+    }
+
+    pub fn ranges(&self) -> &[Range<T>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.ranges.first().map(|range| range.start)
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        let i = self.ranges.partition_point(|range| range.end <= *value);
+        self.ranges.get(i).is_some_and(|range| range.contains(value))
+    }
+
+    pub fn insert(&mut self, range: Range<T>) {
+        *self = self.union(&Self::from_ranges([range]));
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut ranges = Vec::with_capacity(self.ranges.len() + other.ranges.len());
+        let (mut self_iter, mut other_iter) = (self.ranges.iter().peekable(), other.ranges.iter().peekable());
+        while let Some(range) = match (self_iter.peek(), other_iter.peek()) {
+            (Some(a), Some(b)) => if a.start <= b.start { self_iter.next() } else { other_iter.next() },
+            _ => self_iter.next().or_else(|| other_iter.next())
+        } {
+            ranges.push(range.clone());
+        }
+        IntervalSet { ranges: Self::coalesce(ranges) }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while let (Some(a), Some(b)) = (self.ranges.get(i), other.ranges.get(j)) {
+            let (start, end) = (max(a.start, b.start), min(a.end, b.end));
+            if start < end {
+                ranges.push(start..end);
+            }
+            if a.end <= b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let mut j = 0;
+        for range in self.ranges.iter() {
+            let mut start = range.start;
+            while let Some(cut) = other.ranges.get(j).filter(|cut| cut.start < range.end) {
+                if cut.end <= start {
+                    j += 1;
+                    continue;
+                }
+                if start < cut.start {
+                    ranges.push(start..cut.start);
+                }
+                start = max(start, cut.end);
+                if cut.end > range.end {
+                    break;
+                }
+                j += 1;
+            }
+            if start < range.end {
+                ranges.push(start..range.end);
+            }
+        }
+        IntervalSet { ranges }
+    }
+}
+
+impl IntervalSet<u64> {
+    pub fn shift(&self, offset: i64) -> Self {
+        IntervalSet {
+            ranges: self.ranges.iter().map(
+                |range| if let (Some(start), Some(end)) = (range.start.checked_add_signed(offset), range.end.checked_add_signed(offset)) {
+                    start..end
+                } else {
+                    panic!("Shifting {:?} by {} leaves the range of u64", range, offset)
+                }
+            ).collect()
+        }
+    }
+}
+
+impl<T: Ord + Copy> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Copy> FromIterator<Range<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(ranges: I) -> Self {
+        Self::from_ranges(ranges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    const DOMAIN_END: u64 = 64;
+
+    /* xorshift64, so every run checks the same cases */
+    struct Random {
+        state: u64
+    }
+
+    impl Random {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 7;
+            self.state ^= self.state << 17;
+            self.state % bound
+        }
+
+        fn ranges(&mut self) -> Vec<Range<u64>> {
+            (0..self.next(6)).map(
+                |_| {
+                    let start = self.next(DOMAIN_END);
+                    /* includes empty and reversed ranges, which should be ignored */
+                    start..self.next(DOMAIN_END + 1)
+                }
+            ).collect()
+        }
+    }
+
+    fn to_values(set: &IntervalSet<u64>) -> BTreeSet<u64> {
+        set.ranges().iter().flat_map(|range| range.clone()).collect()
+    }
+
+    fn from_values(ranges: &[Range<u64>]) -> BTreeSet<u64> {
+        ranges.iter().flat_map(|range| range.clone()).collect()
+    }
+
+    fn assert_coalesced(set: &IntervalSet<u64>) {
+        assert!(set.ranges().iter().all(|range| range.start < range.end), "{:?} has an empty range", set);
+        assert!(set.ranges().windows(2).all(|pair| pair[0].end < pair[1].start), "{:?} is not sorted and separated", set);
+    }
+
+    fn check_cases(check: impl Fn(&[Range<u64>], &[Range<u64>])) {
+        let mut random = Random { state: 0x2545F4914F6CDD1D };
+        for _ in 0..5000 {
+            let (a, b) = (random.ranges(), random.ranges());
+            check(&a, &b);
+        }
+    }
+
+    #[test]
+    fn from_ranges_coalesces() {
+        check_cases(
+            |a, _| {
+                let set = IntervalSet::from_ranges(a.iter().cloned());
+                assert_coalesced(&set);
+                assert_eq!(to_values(&set), from_values(a));
+                assert_eq!(set.is_empty(), from_values(a).is_empty());
+                assert_eq!(set.min(), from_values(a).first().copied());
+                assert!((0..=DOMAIN_END).all(|value| set.contains(&value) == from_values(a).contains(&value)));
+            }
+        );
+    }
+
+    #[test]
+    fn touching_ranges_merge() {
+        assert_eq!(IntervalSet::from_ranges([5..7, 0..2, 2..5, 9..9, 10..12]).ranges(), &[0..7, 10..12]);
+    }
+
+    #[test]
+    fn union_matches_sets() {
+        check_cases(
+            |a, b| {
+                let union = IntervalSet::from_ranges(a.iter().cloned()).union(&IntervalSet::from_ranges(b.iter().cloned()));
+                assert_coalesced(&union);
+                assert_eq!(to_values(&union), from_values(a).union(&from_values(b)).copied().collect());
+            }
+        );
+    }
+
+    #[test]
+    fn insert_matches_union() {
+        check_cases(
+            |a, b| {
+                let mut set = IntervalSet::from_ranges(a.iter().cloned());
+                for range in b {
+                    set.insert(range.clone());
+                }
+                assert_eq!(set, IntervalSet::from_ranges(a.iter().chain(b.iter()).cloned()));
+            }
+        );
+    }
+
+    #[test]
+    fn intersection_matches_sets() {
+        check_cases(
+            |a, b| {
+                let intersection = IntervalSet::from_ranges(a.iter().cloned()).intersection(&IntervalSet::from_ranges(b.iter().cloned()));
+                assert_coalesced(&intersection);
+                assert_eq!(to_values(&intersection), from_values(a).intersection(&from_values(b)).copied().collect());
+            }
+        );
+    }
+
+    #[test]
+    fn difference_matches_sets() {
+        check_cases(
+            |a, b| {
+                let difference = IntervalSet::from_ranges(a.iter().cloned()).difference(&IntervalSet::from_ranges(b.iter().cloned()));
+                assert_coalesced(&difference);
+                assert_eq!(to_values(&difference), from_values(a).difference(&from_values(b)).copied().collect());
+            }
+        );
+    }
+
+    #[test]
+    fn shift_moves_every_value() {
+        check_cases(
+            |a, b| {
+                let offset = b.first().map_or(0, |range| range.start as i64 - 32);
+                let set = IntervalSet::from_ranges(a.iter().map(|range| (range.start + 32)..(range.end + 32)));
+                let shifted = set.shift(offset);
+                assert_coalesced(&shifted);
+                assert_eq!(to_values(&shifted), to_values(&set).iter().map(|&value| value.checked_add_signed(offset).unwrap()).collect());
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "leaves the range of u64")]
+    fn shift_below_zero_panics() {
+        let range = 3..5;
+        IntervalSet::from_ranges([range]).shift(-4);
+    }
+
+    fn char_grid(text: &str) -> Grid<char> {
+        Grid::from_text(text, |c| c)
+    }
+
+    #[test]
+    fn grid_rotations_and_transposition() {
+        let grid = char_grid("abc\ndef");
+        assert_eq!(grid.rotated_clockwise(), char_grid("da\neb\nfc"));
+        assert_eq!(grid.rotated_counterclockwise(), char_grid("cf\nbe\nad"));
+        assert_eq!(grid.transposed(), char_grid("ad\nbe\ncf"));
+        assert_eq!(grid.rotated_clockwise().rotated_counterclockwise(), grid);
+        assert_eq!(grid.transposed().transposed(), grid);
+    }
+
+    #[test]
+    fn grid_rows_and_columns() {
+        let grid = char_grid("abc\ndef");
+        assert_eq!((grid.num_rows(), grid.num_cols()), (2, 3));
+        assert_eq!(grid.row(1), &['d', 'e', 'f']);
+        assert_eq!(grid.column(2).copied().collect::<String>(), "cf");
+        assert_eq!(grid.columns().map(|column| column.copied().collect::<String>()).collect::<Vec<String>>(), ["ad", "be", "cf"]);
+        assert_eq!(grid[(1, 2)], 'f');
+        assert_eq!(grid.get(2, 0), None);
+        assert_eq!(grid.get(0, 3), None);
+    }
+
+    #[test]
+    fn grid_neighbors_stay_in_bounds() {
+        let grid = char_grid("abc\ndef");
+        assert_eq!(grid.neighbors_4(0, 0).collect::<BTreeSet<(usize, usize)>>(), BTreeSet::from([(0, 1), (1, 0)]));
+        assert_eq!(grid.neighbors_8(0, 0).collect::<BTreeSet<(usize, usize)>>(), BTreeSet::from([(0, 1), (1, 0), (1, 1)]));
+        assert_eq!(grid.neighbors_4(1, 2).collect::<BTreeSet<(usize, usize)>>(), BTreeSet::from([(0, 2), (1, 1)]));
+        assert_eq!(grid.neighbors_8(1, 2).collect::<BTreeSet<(usize, usize)>>(), BTreeSet::from([(0, 1), (0, 2), (1, 1)]));
+        assert_eq!(grid.neighbors_8(0, 1).count(), 5);
+    }
+
+    #[test]
+    fn padded_grids_fill_short_rows() {
+        let grid = Grid::from_text_padded("ab\n\nabcd\r\nc\n\n", '.', |c| c);
+        assert_eq!(grid, char_grid("ab..\n....\nabcd\nc..."));
+    }
+
+    #[test]
+    #[should_panic(expected = "rows of differing lengths")]
+    fn grids_reject_rows_of_differing_lengths() {
+        char_grid("abc\nde");
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of a 2x3 grid")]
+    fn grid_indexing_out_of_bounds_panics() {
+        let _ = char_grid("abc\ndef")[(0, 3)];
+    }
 }
\ No newline at end of file